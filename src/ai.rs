@@ -0,0 +1,688 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::game::{
+    Board, BoardCell, Depth, Grid, PlayerID, Position, ShipKind, ShipOrientation, Shot, Transform,
+};
+use crate::hex;
+use crate::weapons::{Contact, Order, Weapon};
+
+// how the computer opponent picks its shots
+#[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Difficulty {
+    // fire at any cell that hasn't been attacked yet
+    Easy,
+    // hunt on a checkerboard, then finish off ships that were hit
+    Normal,
+    // hunt where ships fit best, leaning toward where this opponent usually
+    // puts them
+    Adaptive,
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Adaptive => "adaptive",
+        }
+    }
+
+    pub fn all() -> Vec<Difficulty> {
+        vec![Difficulty::Easy, Difficulty::Normal, Difficulty::Adaptive]
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::all().into_iter().find(|d| d.name() == name)
+    }
+
+    // the next level up, wrapping around to the easiest
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Adaptive,
+            Difficulty::Adaptive => Difficulty::Easy,
+        }
+    }
+}
+
+// how the computer lays out its fleet
+#[derive(PartialEq, Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Placement {
+    // anywhere on the board
+    Random,
+    // along the edges, where hunters tend to look last
+    Edges,
+    // as far from each other as possible
+    Spread,
+    // close together, so finding one ship doesn't reveal where to look next
+    Clustered,
+    // off the checkerboard most hunters search first
+    AntiParity,
+    // away from the cells the opponent fired at most in earlier games
+    Adaptive,
+}
+
+impl Placement {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Placement::Random => "random",
+            Placement::Edges => "edges",
+            Placement::Spread => "spread",
+            Placement::Clustered => "clustered",
+            Placement::AntiParity => "anti-parity",
+            Placement::Adaptive => "adaptive",
+        }
+    }
+
+    pub fn all() -> Vec<Placement> {
+        vec![
+            Placement::Random,
+            Placement::Edges,
+            Placement::Spread,
+            Placement::Clustered,
+            Placement::AntiParity,
+            Placement::Adaptive,
+        ]
+    }
+
+    // how much the strategy likes a ship on `cells` next to the ones already
+    // placed, higher is better
+    pub fn score(
+        &self,
+        cells: &[Position],
+        placed: &[Position],
+        history: &ShotHistory,
+        grid: Grid,
+    ) -> f64 {
+        let distance = || {
+            cells
+                .iter()
+                .flat_map(|a| {
+                    placed.iter().map(move |b| {
+                        (a.x as i32 - b.x as i32).abs() + (a.y as i32 - b.y as i32).abs()
+                    })
+                })
+                .min()
+                .unwrap_or(0) as f64
+        };
+        match self {
+            Placement::Random => 0.0,
+            Placement::Edges => cells
+                .iter()
+                .filter(|p| p.x == 0 || p.x == 9 || p.y == 0 || p.y == 9)
+                .count() as f64,
+            Placement::Spread => distance(),
+            Placement::Clustered => -distance(),
+            Placement::AntiParity => -(cells.iter().filter(|&&p| grid.parity(p)).count() as f64),
+            Placement::Adaptive => {
+                -(cells
+                    .iter()
+                    .map(|p| history[p.x as usize][p.y as usize])
+                    .sum::<u32>() as f64)
+            }
+        }
+    }
+}
+
+// how often each cell was fired at, indexed [x][y]
+pub type ShotHistory = [[u32; 10]; 10];
+
+// where an opponent put their ships over past games
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LayoutPrior {
+    pub games: u32,
+    // games with a ship on the cell, indexed [x][y]
+    pub cells: ShotHistory,
+}
+
+impl LayoutPrior {
+    pub fn record(&mut self, fleet: &[Position]) {
+        self.games += 1;
+        for p in fleet {
+            self.cells[p.x as usize][p.y as usize] += 1;
+        }
+    }
+
+    // how much more often than not the cell held a ship, 0 without any games
+    fn bias(&self, pos: Position) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.cells[pos.x as usize][pos.y as usize] as f64 / self.games as f64
+        }
+    }
+}
+
+// count the shots `player` fired in a game
+pub fn add_shots(history: &mut ShotHistory, shots: &[Shot], player: PlayerID) {
+    for shot in shots.iter().filter(|s| s.player == player) {
+        history[shot.pos.x as usize][shot.pos.y as usize] += 1;
+    }
+}
+
+// the cells sharing an edge with `pos` on a square grid
+pub fn neighbours(pos: Position) -> Vec<Position> {
+    let mut result = vec![];
+    if pos.x > 0 {
+        result.push(Position {
+            x: pos.x - 1,
+            ..pos
+        });
+    }
+    if pos.x < 9 {
+        result.push(Position {
+            x: pos.x + 1,
+            ..pos
+        });
+    }
+    if pos.y > 0 {
+        result.push(Position {
+            y: pos.y - 1,
+            ..pos
+        });
+    }
+    if pos.y < 9 {
+        result.push(Position {
+            y: pos.y + 1,
+            ..pos
+        });
+    }
+    result
+}
+
+pub fn all_cells() -> impl Iterator<Item = Position> {
+    (0..10).flat_map(|x| {
+        (0..10).map(move |y| Position {
+            x,
+            y,
+            depth: Depth::Surface,
+        })
+    })
+}
+
+fn unknown_cells(view: &Board) -> Vec<Position> {
+    all_cells()
+        .filter(|&p| view.get_cell_value(p) == BoardCell::Empty)
+        .collect()
+}
+
+// cells next to a hit that doesn't belong to a sunk ship, the ones continuing a
+// line of two hits come first
+fn target_cells(view: &Board, sunk: &[Position]) -> Vec<Position> {
+    let is_open_hit = |p: Position| open_hit(view, sunk, p);
+    let mut in_line = vec![];
+    let mut adjacent = vec![];
+    for hit in all_cells().filter(|&p| is_open_hit(p)) {
+        for next in view.grid().neighbours(hit) {
+            if view.get_cell_value(next) != BoardCell::Empty {
+                continue;
+            }
+            // the cell on the other side of the hit, seen from the candidate
+            let behind = view.grid().beyond(next, hit);
+            if behind.x < 10 && behind.y < 10 && is_open_hit(behind) {
+                in_line.push(next);
+            } else {
+                adjacent.push(next);
+            }
+        }
+    }
+    if in_line.is_empty() {
+        adjacent
+    } else {
+        in_line
+    }
+}
+
+// pick the next cell to attack given what the attacker knows: its attack board,
+// the cells of the enemy ships it has sunk, the kinds still afloat and, for the
+// adaptive level, where the opponent placed ships before
+pub fn choose_target(
+    difficulty: Difficulty,
+    view: &Board,
+    sunk: &[Position],
+    afloat: &[ShipKind],
+    prior: &LayoutPrior,
+    r: &mut impl Rng,
+) -> Position {
+    // a repaired hit is a ship for sure, only the easy level doesn't notice
+    let repaired: Vec<Position> = all_cells()
+        .filter(|&p| view.get_cell_value(p) == BoardCell::Repaired)
+        .collect();
+    if difficulty != Difficulty::Easy && !repaired.is_empty() {
+        return *repaired.choose(r).expect("checked above");
+    }
+    let unknown = unknown_cells(view);
+    let candidates = match difficulty {
+        Difficulty::Easy => [unknown, repaired].concat(),
+        Difficulty::Normal => {
+            let targets = target_cells(view, sunk);
+            if !targets.is_empty() {
+                targets
+            } else {
+                // every ship is at least two long, so half the cells are
+                // enough, or two thirds of the hexes
+                let parity: Vec<Position> = unknown
+                    .iter()
+                    .copied()
+                    .filter(|&p| view.grid().parity(p))
+                    .collect();
+                if parity.is_empty() {
+                    unknown
+                } else {
+                    parity
+                }
+            }
+        }
+        Difficulty::Adaptive => {
+            let targets = target_cells(view, sunk);
+            if !targets.is_empty() {
+                targets
+            } else {
+                // the placement density, tripled at most on the cells the
+                // opponent always uses
+                let heat = heatmap(view, sunk, afloat);
+                let score =
+                    |p: Position| heat[p.x as usize][p.y as usize] * (1.0 + 2.0 * prior.bias(p));
+                let best = unknown.iter().map(|&p| score(p)).fold(0.0, f64::max);
+                unknown
+                    .iter()
+                    .copied()
+                    .filter(|&p| score(p) >= best * 0.999)
+                    .collect()
+            }
+        }
+    };
+    *candidates.choose(r).expect("no cell left to attack")
+}
+
+// a suggested shot and the reason for it, in words a player would use
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hint {
+    pub pos: Position,
+    pub reason: String,
+}
+
+// every way a ship of the given kind fits on the board, as its cells
+fn placements(kind: ShipKind, grid: Grid) -> Vec<Vec<Position>> {
+    if grid == Grid::Hex {
+        return all_cells()
+            .flat_map(|p| (0..hex::AXES.len()).map(move |axis| hex::line(p, axis, kind.size())))
+            .filter(|cells| cells.iter().all(|c| c.x < 10 && c.y < 10))
+            .collect();
+    }
+    // turns and mirror images that give the same footprint count once
+    let mut shapes: Vec<Vec<(i8, i8)>> = vec![];
+    for transform in Transform::all() {
+        let mut shape = transform.apply(&kind.shape());
+        shape.sort();
+        if !shapes.contains(&shape) {
+            shapes.push(shape);
+        }
+    }
+    let mut result = vec![];
+    for shape in shapes {
+        for p in all_cells() {
+            let cells: Vec<Position> = shape
+                .iter()
+                .map(|&(dx, dy)| Position {
+                    x: p.x + dx as u8,
+                    y: p.y + dy as u8,
+                    ..p
+                })
+                .collect();
+            if cells.iter().all(|c| c.x < 10 && c.y < 10) {
+                result.push(cells);
+            }
+        }
+    }
+    result
+}
+
+// whether a ship may lie on a cell seen like this, only a miss, an island or a
+// mine rules it out
+fn placeable(cell: BoardCell) -> bool {
+    matches!(
+        cell,
+        BoardCell::Empty | BoardCell::SuccessfulAttack | BoardCell::Repaired | BoardCell::Pending
+    )
+}
+
+// a cell known to hold a ship that isn't sunk: a hit, or one that was repaired
+fn open_hit(view: &Board, sunk: &[Position], p: Position) -> bool {
+    match view.get_cell_value(p) {
+        BoardCell::SuccessfulAttack => !p.overlaps(sunk),
+        BoardCell::Repaired => true,
+        _ => false,
+    }
+}
+
+// the weighted number of placements of a ship of `kind` over every untried
+// cell, and the weight of all of them; with `through_hits` only placements
+// touching an open hit are counted
+fn placement_density(
+    view: &Board,
+    sunk: &[Position],
+    open_hits: &[Position],
+    kind: ShipKind,
+    through_hits: bool,
+) -> ([[u32; 10]; 10], u32) {
+    let mut counts = [[0u32; 10]; 10];
+    let mut total = 0;
+    for cells in placements(kind, view.grid()) {
+        let blocked = cells
+            .iter()
+            .any(|&p| !placeable(view.get_cell_value(p)) || p.overlaps(sunk));
+        let covered = cells.iter().filter(|p| p.overlaps(open_hits)).count() as u32;
+        if blocked || (through_hits && covered == 0) {
+            continue;
+        }
+        // a placement lining up several hits is far more likely than one
+        // crossing a single hit
+        let weight = 4u32.pow(covered);
+        total += weight;
+        for p in cells {
+            if view.get_cell_value(p) == BoardCell::Empty {
+                counts[p.x as usize][p.y as usize] += weight;
+            }
+        }
+    }
+    (counts, total)
+}
+
+// the recommended next shot for a player who sees `view`, knows the cells of
+// the ships they sank and which kinds are still afloat, None once every cell
+// has been attacked
+pub fn hint(view: &Board, sunk: &[Position], afloat: &[ShipKind]) -> Option<Hint> {
+    if let Some(pos) = all_cells().find(|&p| view.get_cell_value(p) == BoardCell::Repaired) {
+        return Some(Hint {
+            pos,
+            reason: format!("{} was repaired, the ship is still there", pos.label()),
+        });
+    }
+    let is_open_hit = |p: Position| open_hit(view, sunk, p);
+    let open_hits: Vec<Position> = all_cells().filter(|&p| is_open_hit(p)).collect();
+
+    // count for every untried cell how many placements of each afloat ship
+    // cover it, while there are open hits only placements through them count
+    let density: Vec<[[u32; 10]; 10]> = afloat
+        .iter()
+        .map(|kind| placement_density(view, sunk, &open_hits, *kind, !open_hits.is_empty()).0)
+        .collect();
+    let total = |p: Position| -> u32 {
+        density
+            .iter()
+            .map(|counts| counts[p.x as usize][p.y as usize])
+            .sum()
+    };
+    let unknown = unknown_cells(view);
+    let best = *unknown
+        .iter()
+        .max_by_key(|&&p| (total(p), std::cmp::Reverse((p.x, p.y))))?;
+    if total(best) == 0 {
+        return Some(Hint {
+            pos: best,
+            reason: String::from("no ship fits anywhere, any untried cell will do"),
+        });
+    }
+
+    let reason = if !open_hits.is_empty() {
+        // a hit next to the cell with another one behind it makes a line
+        let grid = view.grid();
+        let line = grid.neighbours(best).into_iter().find(|&hit| {
+            let behind = grid.beyond(best, hit);
+            is_open_hit(hit) && behind.x < 10 && behind.y < 10 && is_open_hit(behind)
+        });
+        let next_to = grid.neighbours(best).into_iter().find(|&p| is_open_hit(p));
+        match (line, next_to) {
+            (Some(hit), _) if hit.y == best.y => {
+                String::from("continue along the horizontal hit line")
+            }
+            (Some(_), _) if grid == Grid::Hex => {
+                String::from("continue along the diagonal hit line")
+            }
+            (Some(_), _) => String::from("continue along the vertical hit line"),
+            (None, Some(hit)) => format!("{} was hit but not sunk, try next to it", hit.label()),
+            (None, None) => String::from("the damaged ship most likely reaches here"),
+        }
+    } else {
+        // name the ship that contributes most to this cell
+        let (kind, _) = afloat
+            .iter()
+            .zip(density.iter())
+            .max_by_key(|(kind, counts)| (counts[best.x as usize][best.y as usize], kind.size()))?;
+        if afloat.iter().all(|k| k == kind) {
+            format!(
+                "highest placement density for the remaining {}",
+                kind.name()
+            )
+        } else {
+            format!("highest placement density, mostly from the {}", kind.name())
+        }
+    };
+    Some(Hint { pos: best, reason })
+}
+
+// for every cell the chance that it holds a ship as far as the viewer can
+// tell, open hits are certain and misses and sunk ships are empty
+pub fn heatmap(view: &Board, sunk: &[Position], afloat: &[ShipKind]) -> [[f64; 10]; 10] {
+    let open_hits: Vec<Position> = all_cells().filter(|&p| open_hit(view, sunk, p)).collect();
+    // each ship is treated on its own, a cell is empty only if all of them miss it
+    let mut empty = [[1.0f64; 10]; 10];
+    for kind in afloat {
+        let (counts, total) = placement_density(view, sunk, &open_hits, *kind, false);
+        if total == 0 {
+            continue;
+        }
+        for p in all_cells() {
+            let (x, y) = (p.x as usize, p.y as usize);
+            empty[x][y] *= 1.0 - counts[x][y] as f64 / total as f64;
+        }
+    }
+    let mut result = [[0.0; 10]; 10];
+    for p in all_cells() {
+        let (x, y) = (p.x as usize, p.y as usize);
+        result[x][y] = match view.get_cell_value(p) {
+            BoardCell::Empty => 1.0 - empty[x][y],
+            _ if p.overlaps(&open_hits) => 1.0,
+            _ => 0.0,
+        };
+    }
+    result
+}
+
+// expected hits from an airstrike or cluster bomb before it is worth spending
+const STRIKE_WORTH: f64 = 1.8;
+// chance a torpedo finds a ship before it is worth spending while searching
+const TORPEDO_WORTH: f64 = 0.9;
+// sonar is saved until no single cell is more likely than this
+const SONAR_BELOW: f64 = 0.3;
+// how much less likely to be shot a ship has to be before it is moved
+const MOVE_WORTH: f64 = 0.3;
+
+// a special weapon or ability out of `ready` worth using this turn, or a shell
+// into a sonar contact, None leaves the shot to `choose_target`
+pub fn choose_special(
+    view: &Board,
+    sunk: &[Position],
+    afloat: &[ShipKind],
+    ready: &[Weapon],
+    contacts: &[&Contact],
+) -> Option<Order> {
+    if ready.is_empty() && contacts.is_empty() {
+        return None;
+    }
+    let heat = heatmap(view, sunk, afloat);
+    let chance = |p: Position| match view.get_cell_value(p) {
+        BoardCell::Empty => heat[p.x as usize][p.y as usize],
+        BoardCell::Repaired => 1.0,
+        _ => 0.0,
+    };
+    let hunting = !all_cells().any(|p| open_hit(view, sunk, p));
+    // every way to aim the weapon with its value, the best one last
+    let best = |weapon: Weapon, value: &dyn Fn(&Order) -> f64| -> Option<(Order, f64)> {
+        if !ready.contains(&weapon) {
+            return None;
+        }
+        let orientations = if weapon.directional() {
+            vec![ShipOrientation::Horizontal, ShipOrientation::Vertical]
+        } else {
+            vec![ShipOrientation::Horizontal]
+        };
+        all_cells()
+            .flat_map(|target| {
+                orientations.iter().map(move |&orientation| Order {
+                    weapon,
+                    target,
+                    orientation,
+                })
+            })
+            .map(|order| (order, value(&order)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    };
+    let expected_hits = |order: &Order| order.footprint().into_iter().map(chance).sum::<f64>();
+
+    for weapon in [Weapon::Airstrike, Weapon::ClusterBomb] {
+        if let Some((order, hits)) = best(weapon, &expected_hits) {
+            if hits >= STRIKE_WORTH {
+                return Some(order);
+            }
+        }
+    }
+    if !hunting {
+        return None;
+    }
+    // search where the sonar heard something first
+    let in_contact = contacts
+        .iter()
+        .flat_map(|c| c.area.iter().copied())
+        .filter(|&p| view.get_cell_value(p) == BoardCell::Empty)
+        .max_by(|&a, &b| chance(a).total_cmp(&chance(b)));
+    if let Some(pos) = in_contact {
+        return Some(Order::shell(pos));
+    }
+    // the torpedo stops at the first ship, known hits included
+    let torpedo_hit = |order: &Order| {
+        let mut reaches = 1.0;
+        let mut found = 0.0;
+        for p in order.footprint() {
+            if view.get_cell_value(p) == BoardCell::SuccessfulAttack {
+                break;
+            }
+            found += reaches * chance(p);
+            reaches *= 1.0 - chance(p);
+        }
+        found
+    };
+    if let Some((order, found)) = best(Weapon::Torpedo, &torpedo_hit) {
+        if found >= TORPEDO_WORTH {
+            return Some(order);
+        }
+    }
+    let best_shot = all_cells().map(chance).fold(0.0, f64::max);
+    if best_shot < SONAR_BELOW {
+        // the sweep tells most when its answer is hardest to guess
+        let uncertainty = |order: &Order| {
+            let clear: f64 = order
+                .footprint()
+                .into_iter()
+                .map(|p| 1.0 - chance(p))
+                .product();
+            -(clear - 0.5).abs()
+        };
+        // the sweeps are interchangeable, the ability's comes back
+        for weapon in [Weapon::Ping, Weapon::Recon, Weapon::Sonar] {
+            if let Some((order, _)) = best(weapon, &uncertainty) {
+                return Some(order);
+            }
+        }
+    }
+    None
+}
+
+// the move that takes a ship furthest out of harm's way given the chance the
+// opponent would hit each cell, indexed [depth][x][y]; the options are orders
+// with the cells of the ship before and after, None if no move is worth a turn
+pub fn evasion(
+    threat: &[[[f64; 10]; 10]; 2],
+    options: &[(Order, Vec<Position>, Vec<Position>)],
+) -> Option<Order> {
+    let exposure = |cells: &[Position]| -> f64 {
+        cells
+            .iter()
+            .map(|p| threat[p.depth.index()][p.x as usize][p.y as usize])
+            .sum()
+    };
+    options
+        .iter()
+        .map(|(order, before, after)| (*order, exposure(before) - exposure(after)))
+        .filter(|&(_, gain)| gain >= MOVE_WORTH)
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(order, _)| order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: u8, y: u8) -> Position {
+        Position {
+            x,
+            y,
+            depth: Depth::Surface,
+        }
+    }
+
+    #[test]
+    fn a_repaired_cell_is_the_first_hint() {
+        let mut view = Board::new(Grid::Square);
+        view.set_cell(at(2, 2), BoardCell::SuccessfulAttack);
+        view.set_cell(at(7, 4), BoardCell::Repaired);
+        let hint = hint(&view, &[], &[ShipKind::Destroyer]).unwrap();
+        assert_eq!(hint.pos, at(7, 4));
+        assert_eq!(hint.reason, "H5 was repaired, the ship is still there");
+    }
+
+    #[test]
+    fn a_repaired_cell_is_certain_and_draws_the_search() {
+        let mut view = Board::new(Grid::Square);
+        view.set_cell(at(5, 5), BoardCell::Repaired);
+        let heat = heatmap(&view, &[], &[ShipKind::Destroyer]);
+        assert_eq!(heat[5][5], 1.0);
+        assert!(heat[5][6] > heat[0][0]);
+        assert!(heat[4][5] > heat[9][9]);
+    }
+
+    #[test]
+    fn misses_are_empty_and_the_cells_next_to_them_less_likely() {
+        let mut view = Board::new(Grid::Square);
+        view.set_cell(at(0, 1), BoardCell::FailedAttack);
+        let heat = heatmap(&view, &[], &[ShipKind::Destroyer]);
+        assert_eq!(heat[0][1], 0.0);
+        assert!(heat[0][0] < heat[5][5]);
+        assert!(heat[0][0] > 0.0);
+    }
+
+    #[test]
+    fn pending_shots_are_not_searched_but_may_hold_a_ship() {
+        let mut view = Board::new(Grid::Square);
+        view.set_cell(at(9, 9), BoardCell::Pending);
+        let heat = heatmap(&view, &[], &[ShipKind::Destroyer]);
+        assert_eq!(heat[9][9], 0.0);
+        assert!(heat[9][8] > 0.0);
+    }
+
+    #[test]
+    fn hints_follow_a_line_of_hits() {
+        let mut view = Board::new(Grid::Square);
+        view.set_cell(at(4, 4), BoardCell::SuccessfulAttack);
+        view.set_cell(at(5, 4), BoardCell::SuccessfulAttack);
+        let hint = hint(&view, &[], &[ShipKind::Battleship]).unwrap();
+        assert!(hint.pos == at(3, 4) || hint.pos == at(6, 4));
+        assert_eq!(hint.reason, "continue along the horizontal hit line");
+    }
+}
@@ -0,0 +1,196 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::ai;
+use crate::game::{GameState, PlayerID, Position};
+use crate::weapons::{Order, Weapon};
+
+// expected shots lost below which a shot counts as good, and from which on it
+// is a blunder
+const INACCURATE_FROM: f64 = 1.0;
+const BLUNDER_FROM: f64 = 3.0;
+// a cell no ship can be on would take forever, count it as this many shots
+const MAX_SHOTS: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Grade {
+    Good,
+    Inaccurate,
+    Blunder,
+}
+
+impl Grade {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Grade::Good => "good",
+            Grade::Inaccurate => "inaccuracy",
+            Grade::Blunder => "blunder",
+        }
+    }
+
+    // the annotation chess players know
+    pub fn mark(&self) -> &'static str {
+        match self {
+            Grade::Good => "",
+            Grade::Inaccurate => "?!",
+            Grade::Blunder => "??",
+        }
+    }
+}
+
+// one shot next to the best one that was available at the time
+#[derive(Debug, Clone)]
+pub struct ShotReview {
+    pub turn: u32,
+    pub pos: Position,
+    pub hit: bool,
+    pub probability: f64,
+    pub best: Position,
+    pub best_probability: f64,
+    // how many more shots a hit takes on average from the chosen cell
+    pub shots_lost: f64,
+    pub grade: Grade,
+}
+
+// the waiting time for a hit at the given chance, in shots
+fn expected_shots(probability: f64) -> f64 {
+    if probability > 1.0 / MAX_SHOTS {
+        1.0 / probability
+    } else {
+        MAX_SHOTS
+    }
+}
+
+// every shot of one player graded against the heatmap they could have seen
+pub struct Analysis {
+    pub player: PlayerID,
+    pub reviews: Vec<ShotReview>,
+}
+
+impl Analysis {
+    // play the orders again from the fleets as they were laid out, looking at
+    // the heatmap before each of the player's shots; the cells of special
+    // weapons aren't picked one by one so they aren't graded
+    pub fn for_player(finished: &GameState, player: PlayerID) -> Self {
+        let mut game = finished.with_same_fleets();
+        let mut reviews = vec![];
+        for &(by, target, order) in finished.orders() {
+            game.set_aim(by, target);
+            let graded = by == player && matches!(order.weapon, Weapon::Shell | Weapon::DoubleShot);
+            // an order the game doesn't allow changes nothing, like in a replay
+            if !graded {
+                let _ = game.fire(by, order);
+                continue;
+            }
+            // the shot is weighed against the other cells of its layer
+            let depth = order.target.depth;
+            let heat = game.heatmap(player, target, depth);
+            let probability = |p: Position| heat[p.x as usize][p.y as usize];
+            let best = ai::all_cells()
+                .map(|p| p.at(depth))
+                .filter(|&p| game.can_fire(player, &Order::shell(p)))
+                .max_by(|a, b| probability(*a).total_cmp(&probability(*b)))
+                .unwrap_or(order.target);
+            let shots_lost = (expected_shots(probability(order.target))
+                - expected_shots(probability(best)))
+            .max(0.0);
+            let Ok(hit) = game.fire(by, order) else {
+                continue;
+            };
+            let turn = game.shots.last().map(|s| s.turn).unwrap_or(0);
+            let grade = if shots_lost >= BLUNDER_FROM {
+                Grade::Blunder
+            } else if shots_lost >= INACCURATE_FROM {
+                Grade::Inaccurate
+            } else {
+                Grade::Good
+            };
+            reviews.push(ShotReview {
+                turn,
+                pos: order.target,
+                hit,
+                probability: probability(order.target),
+                best,
+                best_probability: probability(best),
+                shots_lost,
+                grade,
+            });
+        }
+        game.drain_events();
+        Analysis { player, reviews }
+    }
+
+    pub fn count(&self, grade: Grade) -> usize {
+        self.reviews.iter().filter(|r| r.grade == grade).count()
+    }
+
+    pub fn shots_lost(&self) -> f64 {
+        self.reviews.iter().map(|r| r.shots_lost).sum()
+    }
+
+    // "31 good, 6 inaccuracies, 2 blunders, 9.4 shots lost"
+    pub fn summary(&self) -> String {
+        format!(
+            "{} good, {} inaccuracies, {} blunders, {:.1} shots lost",
+            self.count(Grade::Good),
+            self.count(Grade::Inaccurate),
+            self.count(Grade::Blunder),
+            self.shots_lost()
+        )
+    }
+
+    // the costliest shots first
+    pub fn worst(&self) -> Vec<&ShotReview> {
+        let mut reviews: Vec<&ShotReview> = self
+            .reviews
+            .iter()
+            .filter(|r| r.grade != Grade::Good)
+            .collect();
+        reviews.sort_by(|a, b| b.shots_lost.total_cmp(&a.shots_lost));
+        reviews
+    }
+
+    // one line per shot, like an annotated chess game
+    pub fn describe(review: &ShotReview) -> String {
+        let mut line = format!(
+            "{:>3}. {:<4}{:<2} {:<4} {:>3.0}%",
+            review.turn,
+            review.pos.label(),
+            review.grade.mark(),
+            if review.hit { "hit" } else { "miss" },
+            review.probability * 100.0
+        );
+        if review.grade != Grade::Good {
+            line.push_str(&format!(
+                "  {}, {} was {:.0}% ({:.1} shots lost)",
+                review.grade.label(),
+                review.best.label(),
+                review.best_probability * 100.0,
+                review.shots_lost
+            ));
+        }
+        line
+    }
+
+    pub fn report(&self, game: &GameState) -> String {
+        let mut text = format!(
+            "Shot analysis for {}, {} rules\n{}\n\n",
+            self.player.name(),
+            game.rules().variant(),
+            self.summary()
+        );
+        for review in &self.reviews {
+            text.push_str(&Analysis::describe(review));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn write(&self, game: &GameState, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, self.report(game))
+    }
+}
@@ -0,0 +1,444 @@
+use std::f64::consts::PI;
+
+use piston_window::*;
+
+use crate::game::{Depth, GameEvent, GameState, Grid, PlayerID, Position};
+use crate::render::{BLOCK_SIZE, ENEMY_OFFSET_X, ENEMY_OFFSET_Y, OWN_OFFSET_X, OWN_OFFSET_Y};
+use crate::theme::Theme;
+
+// shapes the progress of an effect, maps 0..1 onto 0..1
+#[derive(Debug, Clone, Copy)]
+pub enum Easing {
+    Linear,
+    InQuad,
+    OutQuad,
+    InOutCubic,
+    // overshoots a little before settling
+    OutBack,
+}
+
+impl Easing {
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::InQuad => t * t,
+            Easing::OutQuad => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::InOutCubic => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+                }
+            }
+            Easing::OutBack => {
+                let c1 = 1.70158;
+                let c3 = c1 + 1.0;
+                1.0 + c3 * (t - 1.0).powi(3) + c1 * (t - 1.0).powi(2)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum EffectKind {
+    // a shell falling onto the targeted cell
+    Shell { cell: [f64; 2] },
+    // burst of fire over a hit
+    Explosion { cell: [f64; 2] },
+    // rings spreading out from a miss
+    Ripple { cell: [f64; 2] },
+    // the cells of a sunk ship flashing
+    Sink { cells: Vec<[f64; 2]> },
+    // a sonar pulse over the swept area, brighter if it found something
+    Sweep { cells: Vec<[f64; 2]>, found: bool },
+    // a short message announcing whose turn it is
+    Banner { text: String },
+    // discs flying out from the middle of the window
+    Fireworks { won: bool },
+}
+
+#[derive(Debug, Clone)]
+struct Effect {
+    kind: EffectKind,
+    easing: Easing,
+    // seconds to wait before the effect starts
+    delay: f64,
+    duration: f64,
+    elapsed: f64,
+}
+
+impl Effect {
+    fn progress(&self) -> Option<f64> {
+        if self.elapsed < self.delay {
+            None
+        } else {
+            Some(
+                self.easing
+                    .apply((self.elapsed - self.delay) / self.duration),
+            )
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.elapsed >= self.delay + self.duration
+    }
+}
+
+// turns game events into timed visual effects, independent of the game rules
+pub struct Animator {
+    effects: Vec<Effect>,
+    // base length of an effect in seconds
+    duration: f64,
+    // seconds from now until the sequence of queued effects is done
+    tail: f64,
+    // the enemy whose board is shown next to ours, our side, how many fleets
+    // play and the layer of water shown, events on boards we don't see are
+    // left out
+    aimed: PlayerID,
+    allies: Vec<PlayerID>,
+    players: usize,
+    layer: Depth,
+    grid: Grid,
+}
+
+impl Animator {
+    pub fn new(duration_ms: f32) -> Self {
+        Animator {
+            effects: vec![],
+            duration: duration_ms as f64 / 1000.0,
+            tail: 0.0,
+            aimed: PlayerID::P2,
+            allies: vec![PlayerID::P1],
+            players: 2,
+            layer: Depth::Surface,
+            grid: Grid::Square,
+        }
+    }
+
+    pub fn set_duration(&mut self, duration_ms: f32) {
+        self.duration = duration_ms as f64 / 1000.0;
+    }
+
+    // look at the game from P1's side
+    pub fn follow(&mut self, game: &GameState, layer: Depth) {
+        self.aimed = game.aim(PlayerID::P1);
+        self.allies = (0..game.players.len())
+            .map(PlayerID)
+            .filter(|&p| game.allies(PlayerID::P1, p))
+            .collect();
+        self.players = game.players.len();
+        self.layer = layer;
+        self.grid = game.rules().grid;
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+        self.tail = 0.0;
+    }
+
+    // add an effect at the end of the sequence, the next one may start once
+    // `advance` of this one's duration has passed
+    fn queue(&mut self, kind: EffectKind, easing: Easing, length: f64, advance: f64) {
+        let duration = self.duration * length;
+        self.effects.push(Effect {
+            kind,
+            easing,
+            delay: self.tail,
+            duration,
+            elapsed: 0.0,
+        });
+        self.tail += duration * advance;
+    }
+
+    // where a shot of `attacker` at `target` shows: shots at us land on our
+    // board, ours and our teammates' on the enemy board if it is the one shown,
+    // and only on the layer shown; the odd rows of a hex board sit half a cell
+    // to the right
+    fn board_cell(&self, attacker: PlayerID, target: PlayerID, pos: Position) -> Option<[f64; 2]> {
+        let (x, y) = if pos.depth != self.layer {
+            return None;
+        } else if target == PlayerID::P1 {
+            (pos.x as i32 + OWN_OFFSET_X, pos.y as i32 + OWN_OFFSET_Y)
+        } else if target == self.aimed && self.allies.contains(&attacker) {
+            (pos.x as i32 + ENEMY_OFFSET_X, pos.y as i32 + ENEMY_OFFSET_Y)
+        } else {
+            return None;
+        };
+        let shift = match self.grid {
+            Grid::Hex if pos.y % 2 == 1 => 0.5,
+            _ => 0.0,
+        };
+        Some([x as f64 + shift, y as f64])
+    }
+
+    // "Enemy" with a single opponent, "Player 3" with several
+    fn who(&self, player: PlayerID) -> String {
+        if self.players == 2 {
+            String::from("Enemy")
+        } else {
+            player.name()
+        }
+    }
+
+    pub fn handle(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Attack {
+                attacker,
+                target,
+                pos,
+                hit,
+            } => {
+                let Some(cell) = self.board_cell(*attacker, *target, *pos) else {
+                    return;
+                };
+                self.queue(EffectKind::Shell { cell }, Easing::InQuad, 0.5, 1.0);
+                if *hit {
+                    self.queue(EffectKind::Explosion { cell }, Easing::OutQuad, 1.0, 0.4);
+                } else {
+                    self.queue(EffectKind::Ripple { cell }, Easing::Linear, 1.0, 0.4);
+                }
+            }
+            GameEvent::Fired {
+                attacker,
+                target,
+                pos,
+            } => {
+                if let Some(cell) = self.board_cell(*attacker, *target, *pos) {
+                    self.queue(EffectKind::Shell { cell }, Easing::InQuad, 0.5, 1.0);
+                }
+            }
+            GameEvent::Reported {
+                attacker,
+                target,
+                pos,
+                hit,
+            } => {
+                let Some(cell) = self.board_cell(*attacker, *target, *pos) else {
+                    return;
+                };
+                if *hit {
+                    self.queue(EffectKind::Explosion { cell }, Easing::OutQuad, 1.0, 0.4);
+                } else {
+                    self.queue(EffectKind::Ripple { cell }, Easing::Linear, 1.0, 0.4);
+                }
+            }
+            GameEvent::Sunk {
+                attacker,
+                owner,
+                positions,
+                ..
+            } => {
+                let cells: Vec<[f64; 2]> = positions
+                    .iter()
+                    .filter_map(|&p| self.board_cell(*attacker, *owner, p))
+                    .collect();
+                if !cells.is_empty() {
+                    self.queue(EffectKind::Sink { cells }, Easing::Linear, 1.6, 0.8);
+                }
+            }
+            GameEvent::Sonar {
+                scanner,
+                target,
+                area,
+                found,
+            } => {
+                let cells: Vec<[f64; 2]> = area
+                    .iter()
+                    .filter_map(|&p| self.board_cell(*scanner, *target, p))
+                    .collect();
+                let found = *found;
+                if !cells.is_empty() {
+                    self.queue(
+                        EffectKind::Sweep { cells, found },
+                        Easing::OutQuad,
+                        1.6,
+                        0.8,
+                    );
+                }
+            }
+            GameEvent::Moved { owner, kind } => {
+                let text = if *owner == PlayerID::P1 {
+                    format!("Your {} moved", kind.name().to_lowercase())
+                } else {
+                    format!("{} {} moved", self.who(*owner), kind.name().to_lowercase())
+                };
+                self.queue(EffectKind::Banner { text }, Easing::InOutCubic, 1.2, 0.3);
+            }
+            GameEvent::Repaired { owner, kind, pos } => {
+                let text = if *owner == PlayerID::P1 {
+                    format!("Your {} was repaired", kind.name().to_lowercase())
+                } else {
+                    format!("{} repaired a hit on {}", self.who(*owner), pos.label())
+                };
+                self.queue(EffectKind::Banner { text }, Easing::InOutCubic, 1.2, 0.3);
+            }
+            GameEvent::Mine {
+                attacker,
+                target,
+                pos,
+                ..
+            } => {
+                if let Some(cell) = self.board_cell(*attacker, *target, *pos) {
+                    self.queue(EffectKind::Explosion { cell }, Easing::OutQuad, 1.0, 0.4);
+                }
+                let text = if *attacker == PlayerID::P1 {
+                    String::from("You hit a mine")
+                } else {
+                    format!("{} hit a mine", self.who(*attacker))
+                };
+                self.queue(EffectKind::Banner { text }, Easing::InOutCubic, 1.2, 0.3);
+            }
+            GameEvent::Eliminated(player) => {
+                let text = if *player == PlayerID::P1 {
+                    String::from("Your fleet is gone")
+                } else {
+                    format!("{} is out", self.who(*player))
+                };
+                self.queue(EffectKind::Banner { text }, Easing::InOutCubic, 1.2, 0.3);
+            }
+            GameEvent::TurnChanged(player) => {
+                let text = if *player == PlayerID::P1 {
+                    String::from("Your turn")
+                } else {
+                    format!("{}'s turn", self.who(*player))
+                };
+                self.queue(EffectKind::Banner { text }, Easing::InOutCubic, 1.2, 0.3);
+            }
+            GameEvent::Victory(winner) => {
+                // the boards are gone, so drop whatever was still playing on them
+                self.clear();
+                let won = self.allies.contains(winner);
+                self.queue(EffectKind::Fireworks { won }, Easing::OutBack, 3.0, 1.0);
+            }
+        }
+    }
+
+    // advance every effect by the seconds since the last update
+    pub fn update(&mut self, dt: f64) {
+        for effect in &mut self.effects {
+            effect.elapsed += dt;
+        }
+        self.effects.retain(|e| !e.finished());
+        self.tail = (self.tail - dt).max(0.0);
+    }
+
+    pub fn render(&self, con: &Context, g: &mut G2d, glyphs: &mut Glyphs, theme: &Theme) {
+        for effect in &self.effects {
+            if let Some(t) = effect.progress() {
+                render_effect(con, g, glyphs, theme, &effect.kind, t);
+            }
+        }
+    }
+}
+
+// square centred on a cell, `scale` times the size of the cell
+fn centred(cell: [f64; 2], scale: f64) -> [f64; 4] {
+    let size = BLOCK_SIZE * scale;
+    let cx = (cell[0] + 0.5) * BLOCK_SIZE;
+    let cy = (cell[1] + 0.5) * BLOCK_SIZE;
+    [cx - size / 2.0, cy - size / 2.0, size, size]
+}
+
+fn with_alpha(color: [f32; 4], alpha: f64) -> [f32; 4] {
+    [
+        color[0],
+        color[1],
+        color[2],
+        color[3] * alpha.clamp(0.0, 1.0) as f32,
+    ]
+}
+
+fn render_effect(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    kind: &EffectKind,
+    t: f64,
+) {
+    match kind {
+        EffectKind::Shell { cell } => {
+            // shrinks and darkens as it comes down
+            ellipse(
+                with_alpha(theme.splash, t),
+                centred(*cell, 1.4 - 0.7 * t),
+                con.transform,
+                g,
+            );
+        }
+        EffectKind::Explosion { cell } => {
+            ellipse(
+                with_alpha(theme.hit, 1.0 - t),
+                centred(*cell, 0.6 + 1.2 * t),
+                con.transform,
+                g,
+            );
+            Ellipse::new_border(with_alpha(theme.last_shot, 1.0 - t), 2.0).draw(
+                centred(*cell, 0.4 + 1.8 * t),
+                &con.draw_state,
+                con.transform,
+                g,
+            );
+        }
+        EffectKind::Ripple { cell } => {
+            for ring in 0..2 {
+                let r = (t - ring as f64 * 0.3).max(0.0);
+                Ellipse::new_border(with_alpha(theme.splash, 1.0 - t), 1.0).draw(
+                    centred(*cell, 0.3 + 1.5 * r),
+                    &con.draw_state,
+                    con.transform,
+                    g,
+                );
+            }
+        }
+        EffectKind::Sweep { cells, found } => {
+            let color = if *found { theme.hint } else { theme.splash };
+            for &cell in cells {
+                Ellipse::new_border(with_alpha(color, 1.0 - t), 1.5).draw(
+                    centred(cell, 0.2 + 0.8 * t),
+                    &con.draw_state,
+                    con.transform,
+                    g,
+                );
+            }
+        }
+        EffectKind::Sink { cells } => {
+            // three flashes fading out
+            let alpha = (1.0 - t) * (t * 3.0 * PI).sin().abs();
+            for &cell in cells {
+                rectangle(
+                    with_alpha(theme.hit, alpha),
+                    centred(cell, 1.0),
+                    con.transform,
+                    g,
+                );
+            }
+        }
+        EffectKind::Banner { text } => {
+            // fade in, hold, fade out
+            let alpha = (t * PI).sin();
+            let transform = con.transform.trans(BLOCK_SIZE * 14.5, BLOCK_SIZE * 17.0);
+            text::Text::new_color(with_alpha(theme.title, alpha), theme.heading_size)
+                .draw(text, glyphs, &con.draw_state, transform, g)
+                .unwrap();
+        }
+        EffectKind::Fireworks { won } => {
+            let color = if *won { theme.last_shot } else { theme.sunk };
+            let (cx, cy) = (BLOCK_SIZE * 19.0, BLOCK_SIZE * 9.5);
+            for i in 0..12 {
+                let angle = i as f64 / 12.0 * 2.0 * PI;
+                let distance = BLOCK_SIZE * 7.0 * t;
+                let size = BLOCK_SIZE * 0.5;
+                let x = cx + angle.cos() * distance - size / 2.0;
+                let y = cy + angle.sin() * distance - size / 2.0;
+                ellipse(
+                    with_alpha(color, 1.2 - t),
+                    [x, y, size, size],
+                    con.transform,
+                    g,
+                );
+            }
+        }
+    }
+}
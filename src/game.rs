@@ -0,0 +1,2293 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::ops::Not;
+use std::time::{Duration, Instant};
+
+use crate::ai::{self, Difficulty, Hint, LayoutPrior, Placement, ShotHistory};
+use crate::hex;
+use crate::weapons::{Arsenal, Contact, Order, Weapon};
+
+// legal spots tried per ship when a placement strategy picks the best one
+const PLACEMENT_CANDIDATES: u32 = 40;
+// with a mobile fleet the AI forgets a miss once the opponent has manoeuvred
+// this many times since it was fired
+const TRUST_MANEUVERS: u32 = 3;
+// with terrain, the islands shared by both boards and the mines each player lays
+const ISLANDS: usize = 3;
+const MINES: usize = 3;
+
+// the layer of water a cell is in, only the 3D variant goes below the surface
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub enum Depth {
+    #[default]
+    Surface,
+    Submerged,
+}
+
+impl Depth {
+    pub fn all() -> [Depth; 2] {
+        [Depth::Surface, Depth::Submerged]
+    }
+
+    pub fn index(&self) -> usize {
+        *self as usize
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Depth::Surface => "Surface",
+            Depth::Submerged => "Submerged",
+        }
+    }
+}
+
+impl Not for Depth {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        match self {
+            Depth::Surface => Depth::Submerged,
+            Depth::Submerged => Depth::Surface,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Position {
+    pub x: u8,
+    pub y: u8,
+    pub depth: Depth,
+}
+
+impl Position {
+    pub fn random<R: Rng>(r: &mut R) -> Self {
+        Position {
+            x: r.gen_range(0..10),
+            y: r.gen_range(0..10),
+            depth: Depth::Surface,
+        }
+    }
+
+    // the cell above or below this one
+    pub fn at(&self, depth: Depth) -> Position {
+        Position { depth, ..*self }
+    }
+
+    pub fn overlaps(&self, positions: &[Position]) -> bool {
+        positions.contains(self)
+    }
+
+    // the name players use for the cell, like "B7", or "B7 below" under the
+    // surface
+    pub fn label(&self) -> String {
+        let name = format!("{}{}", (b'A' + self.x) as char, self.y + 1);
+        match self.depth {
+            Depth::Surface => name,
+            Depth::Submerged => format!("{} below", name),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ShipOrientation {
+    Horizontal,
+    Vertical,
+}
+
+impl Not for ShipOrientation {
+    type Output = Self;
+    fn not(self) -> Self::Output {
+        match self {
+            ShipOrientation::Horizontal => ShipOrientation::Vertical,
+            ShipOrientation::Vertical => ShipOrientation::Horizontal,
+        }
+    }
+}
+
+// how a ship's shape is laid on the board: mirrored first if asked, then
+// turned clockwise a number of quarter turns
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Transform {
+    pub turns: u8,
+    pub mirrored: bool,
+}
+
+impl Transform {
+    pub fn random<R: Rng>(r: &mut R) -> Self {
+        Transform {
+            turns: r.gen_range(0..4),
+            mirrored: r.gen_bool(0.5),
+        }
+    }
+
+    pub fn all() -> Vec<Transform> {
+        (0..8)
+            .map(|i| Transform {
+                turns: i % 4,
+                mirrored: i >= 4,
+            })
+            .collect()
+    }
+
+    // the cells of `shape` after the transform, moved so the corner of their
+    // bounding box is at the origin
+    pub fn apply(&self, shape: &[(i8, i8)]) -> Vec<(i8, i8)> {
+        let cells: Vec<(i8, i8)> = shape
+            .iter()
+            .map(|&(x, y)| if self.mirrored { (-x, y) } else { (x, y) })
+            .map(|cell| (0..self.turns).fold(cell, |(x, y), _| (-y, x)))
+            .collect();
+        let min_x = cells.iter().map(|c| c.0).min().unwrap_or(0);
+        let min_y = cells.iter().map(|c| c.1).min().unwrap_or(0);
+        cells
+            .into_iter()
+            .map(|(x, y)| (x - min_x, y - min_y))
+            .collect()
+    }
+}
+
+// a straight ship lies along the orientation without being mirrored
+impl From<ShipOrientation> for Transform {
+    fn from(orientation: ShipOrientation) -> Self {
+        let turns = match orientation {
+            ShipOrientation::Horizontal => 0,
+            ShipOrientation::Vertical => 1,
+        };
+        Transform {
+            turns,
+            mirrored: false,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ShipStatus {
+    Undamaged,
+    Damaged,
+    Sunk,
+}
+
+impl ShipStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ShipStatus::Undamaged => "afloat",
+            ShipStatus::Damaged => "damaged",
+            ShipStatus::Sunk => "sunk",
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShipKind {
+    Patrol,
+    Submarine,
+    Destroyer,
+    Battleship,
+    Carrier,
+    // bent ships for variant fleets, named after the letter they look like
+    #[serde(rename = "l-ship")]
+    LShip,
+    #[serde(rename = "t-ship")]
+    TShip,
+    #[serde(rename = "z-ship")]
+    ZShip,
+}
+
+impl ShipKind {
+    pub fn size(&self) -> u8 {
+        match self {
+            ShipKind::Patrol => 2,
+            ShipKind::Submarine => 3,
+            ShipKind::Destroyer => 3,
+            ShipKind::Battleship => 4,
+            ShipKind::Carrier => 5,
+            ShipKind::LShip | ShipKind::TShip | ShipKind::ZShip => 4,
+        }
+    }
+
+    // the cells the ship covers before it is turned, a straight ship lies
+    // horizontally from the origin
+    pub fn shape(&self) -> Vec<(i8, i8)> {
+        match self {
+            ShipKind::LShip => vec![(0, 0), (1, 0), (2, 0), (2, 1)],
+            ShipKind::TShip => vec![(0, 0), (1, 0), (2, 0), (1, 1)],
+            ShipKind::ZShip => vec![(0, 0), (1, 0), (1, 1), (2, 1)],
+            _ => (0..self.size() as i8).map(|i| (i, 0)).collect(),
+        }
+    }
+
+    pub fn straight(&self) -> bool {
+        !matches!(self, ShipKind::LShip | ShipKind::TShip | ShipKind::ZShip)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShipKind::Patrol => "Patrol boat",
+            ShipKind::Submarine => "Submarine",
+            ShipKind::Destroyer => "Destroyer",
+            ShipKind::Battleship => "Battleship",
+            ShipKind::Carrier => "Carrier",
+            ShipKind::LShip => "L-ship",
+            ShipKind::TShip => "T-ship",
+            ShipKind::ZShip => "Z-ship",
+        }
+    }
+
+    // what the ship can do while afloat when abilities are played
+    pub fn ability(&self) -> Option<Weapon> {
+        match self {
+            ShipKind::Patrol | ShipKind::LShip | ShipKind::TShip | ShipKind::ZShip => None,
+            ShipKind::Submarine => Some(Weapon::Ping),
+            ShipKind::Destroyer => Some(Weapon::Move),
+            ShipKind::Battleship => Some(Weapon::DoubleShot),
+            ShipKind::Carrier => Some(Weapon::Recon),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum BoardCell {
+    Empty,
+    Ship,
+    DamagedShip,
+    FailedAttack,
+    SuccessfulAttack,
+    // an island or reef, on the same cells of every board; no ship is placed
+    // there and nobody fires at it
+    Island,
+    // a mine on its owner's ship board, and on the attack board of whoever
+    // set it off
+    Mine,
+    // on an attack board, a hit the owner has repaired since; the ship is
+    // still there and can be hit again
+    Repaired,
+    // with fog of war, a shot whose result hasn't been reported yet; only in
+    // what a player sees, never on the boards themselves
+    Pending,
+}
+
+// the shape of the cells of every board
+#[derive(PartialEq, Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grid {
+    Square,
+    // every cell has six neighbours and the odd rows sit half a cell to the
+    // right, ships lie along the row or down either diagonal
+    Hex,
+}
+
+impl Grid {
+    // the cells sharing an edge with `pos`
+    pub fn neighbours(&self, pos: Position) -> Vec<Position> {
+        match self {
+            Grid::Square => ai::neighbours(pos),
+            Grid::Hex => hex::neighbours(pos),
+        }
+    }
+
+    // the cell and every one touching it, corners included on a square grid,
+    // some may be off the board
+    pub fn around(&self, pos: Position) -> Vec<Position> {
+        match self {
+            Grid::Square => (-1i8..=1)
+                .flat_map(|dx| {
+                    (-1i8..=1).map(move |dy| Position {
+                        x: (pos.x as i8 + dx) as u8,
+                        y: (pos.y as i8 + dy) as u8,
+                        ..pos
+                    })
+                })
+                .collect(),
+            Grid::Hex => [vec![pos], hex::neighbours(pos)].concat(),
+        }
+    }
+
+    // the cell after `to` on the line from its neighbour `from`, may be off
+    // the board
+    pub fn beyond(&self, from: Position, to: Position) -> Position {
+        match self {
+            Grid::Square => Position {
+                x: (2 * to.x as i8 - from.x as i8) as u8,
+                y: (2 * to.y as i8 - from.y as i8) as u8,
+                ..to
+            },
+            Grid::Hex => hex::beyond(from, to),
+        }
+    }
+
+    // the cells hunters search first, enough to find any ship of two or more:
+    // a checkerboard, or two cells out of three on hexes
+    pub fn parity(&self, pos: Position) -> bool {
+        match self {
+            Grid::Square => (pos.x + pos.y).is_multiple_of(2),
+            Grid::Hex => hex::parity(pos),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Board {
+    // indexed [depth][x][y], the submerged layer stays empty outside 3D games
+    cells: [[[BoardCell; 10]; 10]; 2],
+    grid: Grid,
+}
+
+impl Board {
+    pub fn new(grid: Grid) -> Self {
+        Board {
+            cells: [[[BoardCell::Empty; 10]; 10]; 2],
+            grid,
+        }
+    }
+
+    pub fn grid(&self) -> Grid {
+        self.grid
+    }
+
+    pub fn set_cell(&mut self, pos: Position, value: BoardCell) {
+        self.cells[pos.depth.index()][pos.x as usize][pos.y as usize] = value;
+    }
+
+    pub fn get_cell_value(&self, pos: Position) -> BoardCell {
+        self.cells[pos.depth.index()][pos.x as usize][pos.y as usize]
+    }
+
+    // one layer of the board moved up to the surface, for drawing it and for
+    // the AI, which only knows flat boards
+    pub fn layer(&self, depth: Depth) -> Board {
+        let mut board = Board::new(self.grid);
+        board.cells[0] = self.cells[depth.index()];
+        board
+    }
+
+    // a ship board with the misses from the opponent's attack board laid over it,
+    // shows everything once the game is over
+    pub fn revealed(ships: &Board, attacks: &Board) -> Board {
+        let mut board = *ships;
+        for depth in Depth::all() {
+            for pos in ai::all_cells().map(|p| p.at(depth)) {
+                if attacks.get_cell_value(pos) == BoardCell::FailedAttack {
+                    board.set_cell(pos, BoardCell::FailedAttack);
+                }
+            }
+        }
+        board
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Ship {
+    pub kind: ShipKind,
+    pub size: u8,
+    position: Vec<Position>,
+    // whether each cell, in the order of `position`, has been hit
+    damage: Vec<bool>,
+    pub status: ShipStatus,
+    // turns of its owner until the ship's ability can be used again
+    pub cooldown: u32,
+}
+
+impl Ship {
+    pub fn new(kind: ShipKind) -> Self {
+        Self {
+            kind,
+            size: kind.size(),
+            position: vec![],
+            damage: vec![],
+            status: ShipStatus::Undamaged,
+            cooldown: 0,
+        }
+    }
+
+    // lay the ship's shape with the corner of its bounding box on `pos`
+    pub fn place_at(&mut self, pos: Position, transform: Transform) -> &mut Self {
+        self.position = transform
+            .apply(&self.kind.shape())
+            .into_iter()
+            .map(|(dx, dy)| Position {
+                x: pos.x + dx as u8,
+                y: pos.y + dy as u8,
+                ..pos
+            })
+            .collect();
+        self.damage = vec![false; self.position.len()];
+
+        self
+    }
+
+    // lay a straight ship on a hex board from `pos` along one of the three axes
+    pub fn place_along(&mut self, pos: Position, axis: usize) -> &mut Self {
+        self.position = hex::line(pos, axis, self.size);
+        self.damage = vec![false; self.position.len()];
+
+        self
+    }
+
+    pub fn positions(&self) -> Vec<Position> {
+        self.position.clone()
+    }
+    pub fn intersects(&self, positions: &[Position]) -> bool {
+        self.position.iter().any(|&p| p.overlaps(positions))
+    }
+
+    // the cells that have been hit and not repaired
+    pub fn damaged_cells(&self) -> Vec<Position> {
+        self.position
+            .iter()
+            .zip(&self.damage)
+            .filter(|(_, &hit)| hit)
+            .map(|(&p, _)| p)
+            .collect()
+    }
+
+    // recompute the damage and status from the owner's ship board
+    pub fn update_status(&mut self, board: &Board) {
+        self.damage = self
+            .position
+            .iter()
+            .map(|&p| board.get_cell_value(p) == BoardCell::DamagedShip)
+            .collect();
+        let hits = self.damage.iter().filter(|&&hit| hit).count();
+        self.status = if hits == 0 {
+            ShipStatus::Undamaged
+        } else if hits < self.position.len() {
+            ShipStatus::Damaged
+        } else {
+            ShipStatus::Sunk
+        };
+    }
+
+    pub fn is_sunk(&self) -> bool {
+        self.status == ShipStatus::Sunk
+    }
+}
+
+#[derive(Clone)]
+pub struct Player {
+    ships: Vec<Ship>,
+}
+
+impl Player {
+    pub fn new() -> Self {
+        Player { ships: vec![] }
+    }
+
+    pub fn all_ship_positions(&self) -> Vec<Position> {
+        self.ships().iter().flat_map(|s| s.positions()).collect()
+    }
+
+    pub fn add_ship(&mut self, ship: Ship) {
+        self.ships.push(ship)
+    }
+
+    pub fn ships(&self) -> &Vec<Ship> {
+        &self.ships
+    }
+
+    pub fn ship_at(&self, pos: Position) -> Option<&Ship> {
+        self.ships.iter().find(|s| s.position.contains(&pos))
+    }
+
+    // refresh the status of the ship occupying the given position, if any
+    pub fn update_ship_at(&mut self, pos: Position, board: &Board) {
+        if let Some(ship) = self.ships.iter_mut().find(|s| s.position.contains(&pos)) {
+            ship.update_status(board);
+        }
+    }
+}
+
+// what setting off a mine costs the player who fired
+#[derive(PartialEq, Debug, Copy, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MinePenalty {
+    // they sit out their next turn
+    Skip,
+    // the mine's owner learns where one of their ships is
+    Reveal,
+}
+
+// the fleet and placement rules a game is played with
+#[derive(PartialEq, Debug, Clone)]
+pub struct Rules {
+    pub fleet: Vec<ShipKind>,
+    pub ships_may_touch: bool,
+    // sonar, airstrikes, torpedoes and cluster bombs next to the shell
+    pub special_weapons: bool,
+    // every ship that is afloat lends its owner an ability
+    pub ship_abilities: bool,
+    // instead of firing a player may move or turn one of their undamaged ships
+    pub mobile_fleet: bool,
+    // islands on both boards and a few mines laid by each player
+    pub terrain: bool,
+    pub mine_penalty: MinePenalty,
+    // a player may spend a turn mending a hit on a ship that is afloat
+    pub repairs: bool,
+    // the results of a turn's shots are only reported after the next turn
+    pub fog: bool,
+    // how many fleets take part, from two up to six
+    pub players: usize,
+    // four players in two teams that take turns, the odd seats against the even
+    pub teams: bool,
+    // the water has a submerged layer below the surface where the submarines
+    // sail, and some weapons only reach one of the two
+    pub depth: bool,
+    pub grid: Grid,
+}
+
+impl Rules {
+    pub fn new() -> Self {
+        Rules {
+            fleet: vec![
+                ShipKind::Submarine,
+                ShipKind::Patrol,
+                ShipKind::Destroyer,
+                ShipKind::Battleship,
+                ShipKind::Carrier,
+            ],
+            ships_may_touch: true,
+            special_weapons: false,
+            ship_abilities: false,
+            mobile_fleet: false,
+            terrain: false,
+            mine_penalty: MinePenalty::Skip,
+            repairs: false,
+            fog: false,
+            players: 2,
+            teams: false,
+            depth: false,
+            grid: Grid::Square,
+        }
+    }
+
+    // the layers of water ships sail in
+    pub fn layers(&self) -> Vec<Depth> {
+        if self.depth {
+            Depth::all().to_vec()
+        } else {
+            vec![Depth::Surface]
+        }
+    }
+
+    // the layer a ship of the kind sails in, submarines dive in 3D games
+    pub fn depth_of(&self, kind: ShipKind) -> Depth {
+        if self.depth && kind == ShipKind::Submarine {
+            Depth::Submerged
+        } else {
+            Depth::Surface
+        }
+    }
+
+    // the side `player` is on, every player is on their own without teams
+    pub fn team(&self, player: PlayerID) -> usize {
+        if self.teams {
+            player.index() % 2
+        } else {
+            player.index()
+        }
+    }
+
+    // short name of the rule variant, used to keep statistics apart
+    pub fn variant(&self) -> String {
+        let mut tags = vec![];
+        if self.fleet != Rules::new().fleet {
+            tags.push("custom-fleet");
+        }
+        if !self.ships_may_touch {
+            tags.push("no-touch");
+        }
+        if self.special_weapons {
+            tags.push("weapons");
+        }
+        if self.ship_abilities {
+            tags.push("abilities");
+        }
+        if self.mobile_fleet {
+            tags.push("mobile");
+        }
+        if self.terrain {
+            tags.push(match self.mine_penalty {
+                MinePenalty::Skip => "terrain",
+                MinePenalty::Reveal => "terrain-reveal",
+            });
+        }
+        if self.repairs {
+            tags.push("repair");
+        }
+        if self.fog {
+            tags.push("fog");
+        }
+        if self.depth {
+            tags.push("3d");
+        }
+        if self.grid == Grid::Hex {
+            tags.push("hex");
+        }
+        let seats = format!("ffa-{}", self.players);
+        if self.teams {
+            tags.push("teams");
+        } else if self.players > 2 {
+            tags.push(&seats);
+        }
+        if tags.is_empty() {
+            String::from("classic")
+        } else {
+            tags.join("+")
+        }
+    }
+
+    // make sure a fleet can always be placed randomly on the board
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=6).contains(&self.players) {
+            return Err(format!("{} players, a game takes 2 to 6", self.players));
+        }
+        if self.teams && self.players != 4 {
+            return Err(String::from("team games are played by 4 players"));
+        }
+        if self.fleet.is_empty() {
+            return Err(String::from("the fleet needs at least one ship"));
+        }
+        // ships only turn and weapons only aim along the square grid
+        if self.grid == Grid::Hex {
+            if let Some(kind) = self.fleet.iter().find(|k| !k.straight()) {
+                return Err(format!("the {} only fits a square grid", kind.name()));
+            }
+            if self.special_weapons || self.ship_abilities || self.mobile_fleet {
+                return Err(String::from(
+                    "hex boards are played without special weapons, ship abilities or a mobile fleet",
+                ));
+            }
+        }
+        if self.fleet.len() > 10 {
+            return Err(format!("{} ships is more than 10", self.fleet.len()));
+        }
+        let cells: u32 = self.fleet.iter().map(|k| k.size() as u32).sum();
+        let mut limit = if self.ships_may_touch { 50 } else { 30 };
+        // an island takes up to two cells and the mines go where ships aren't
+        if self.terrain {
+            limit -= 2 * ISLANDS as u32 + MINES as u32;
+        }
+        if cells > limit {
+            return Err(format!(
+                "the fleet covers {} cells, at most {} fit on the board",
+                cells, limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum GameStatus {
+    NotStarted,
+    InProgress,
+    Complete(PlayerID),
+}
+
+// the seat of a player, in turn order
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct PlayerID(pub usize);
+
+impl PlayerID {
+    // the one at the window, and the computer of a two-player game
+    pub const P1: PlayerID = PlayerID(0);
+    pub const P2: PlayerID = PlayerID(1);
+
+    pub fn index(&self) -> usize {
+        self.0
+    }
+
+    // "Player 3"
+    pub fn name(&self) -> String {
+        format!("Player {}", self.0 + 1)
+    }
+}
+
+// one entry of the shot log
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Shot {
+    pub player: PlayerID,
+    // whose board it was fired at
+    pub target: PlayerID,
+    pub pos: Position,
+    pub hit: bool,
+    pub sunk: Option<ShipKind>,
+    // how many shots the player had fired, this one included
+    pub turn: u32,
+    // a shot of a special weapon hits several cells, each is logged on its own
+    pub weapon: Weapon,
+}
+
+// an order as it was given: who gave it and whose board it was aimed at
+pub type LoggedOrder = (PlayerID, PlayerID, Order);
+
+// what happened in the game, collected for the frontend to react to
+#[derive(PartialEq, Debug, Clone)]
+pub enum GameEvent {
+    Attack {
+        attacker: PlayerID,
+        target: PlayerID,
+        pos: Position,
+        hit: bool,
+    },
+    // with fog of war, a shot went out and its result comes with a later
+    // `Reported`
+    Fired {
+        attacker: PlayerID,
+        target: PlayerID,
+        pos: Position,
+    },
+    Reported {
+        attacker: PlayerID,
+        target: PlayerID,
+        pos: Position,
+        hit: bool,
+    },
+    Sunk {
+        attacker: PlayerID,
+        owner: PlayerID,
+        kind: ShipKind,
+        positions: Vec<Position>,
+    },
+    // a sonar sweep, `found` if a ship that hasn't been hit is in the area
+    Sonar {
+        scanner: PlayerID,
+        target: PlayerID,
+        area: Vec<Position>,
+        found: bool,
+    },
+    // a ship moved with its ability, where to stays with its owner
+    Moved {
+        owner: PlayerID,
+        kind: ShipKind,
+    },
+    // a hit was repaired, the opponent sees the cell open up again
+    Repaired {
+        owner: PlayerID,
+        kind: ShipKind,
+        pos: Position,
+    },
+    // a shot set off a mine, and the ship cell it gave away if it did
+    Mine {
+        attacker: PlayerID,
+        target: PlayerID,
+        pos: Position,
+        revealed: Option<Position>,
+    },
+    // the last ship of a player went down, they take no more turns
+    Eliminated(PlayerID),
+    TurnChanged(PlayerID),
+    Victory(PlayerID),
+}
+
+#[derive(Clone)]
+pub struct GameState {
+    pub players: Vec<Player>,
+    pub ships: Vec<Board>,
+    // what each player knows of every other player's board, [shooter][target]
+    pub attacks: Vec<Vec<Board>>,
+    // p1: Player,
+    // p2: Player,
+    // p1_attack_board: Board,
+    // p1_ship_board: Board,
+    // p2_attack_board: Board,
+    // p2_ship_board: Board,
+    pub status: GameStatus,
+    // most recent shot fired by each player and whose board it hit
+    pub last_attacks: Vec<Option<(PlayerID, Position)>>,
+    pub turn: PlayerID,
+    pub shots: Vec<Shot>,
+    rules: Rules,
+    // whose board each player fires at next
+    aims: Vec<PlayerID>,
+    // players whose fleet is gone
+    out: Vec<bool>,
+    // what each player's AI has learned about how the opponent lays out ships
+    priors: Vec<LayoutPrior>,
+    // the special weapons each player has left and where their sonar found
+    // ships, per target
+    arsenals: Vec<Arsenal>,
+    contacts: Vec<Vec<Vec<Contact>>>,
+    // the fleets, arsenals and generator as they were once the fleets were laid
+    // out and every order since with the board it was aimed at, enough to play
+    // the game again even when ships moved
+    opening: Vec<Vec<Ship>>,
+    opening_arsenals: Vec<Arsenal>,
+    opening_rng: StdRng,
+    orders: Vec<LoggedOrder>,
+    // how often each player manoeuvred, and for every miss how often the
+    // target had manoeuvred when it was fired, [shooter][target][depth]
+    maneuvers: Vec<u32>,
+    miss_epochs: Vec<Vec<[[[u32; 10]; 10]; 2]>>,
+    // players who set off a mine and sit out their next turn
+    skips: Vec<bool>,
+    // with fog of war, the shots of each player that haven't been reported, per
+    // target, and the events that will tell them what happened
+    fog: Vec<Vec<Vec<Position>>>,
+    delayed: Vec<Vec<GameEvent>>,
+    events: Vec<GameEvent>,
+    started_at: Option<Instant>,
+    finished_at: Option<Instant>,
+    // every random choice of the game comes from here and the computer's from
+    // its own generator, so a copy of the state plays on exactly like the
+    // original and the orders alone replay the game's choices
+    rng: StdRng,
+    ai_rng: StdRng,
+}
+
+impl GameState {
+    pub fn initialize(rules: Rules) -> Self {
+        let n = rules.players;
+        let mut game = GameState {
+            players: vec![Player::new(); n],
+            attacks: vec![vec![Board::new(rules.grid); n]; n],
+            ships: vec![Board::new(rules.grid); n],
+            status: GameStatus::NotStarted,
+            last_attacks: vec![None; n],
+            turn: PlayerID::P1,
+            shots: vec![],
+            aims: vec![PlayerID::P1; n],
+            out: vec![false; n],
+            priors: vec![LayoutPrior::default(); n],
+            arsenals: vec![Arsenal::empty(); n],
+            contacts: vec![vec![vec![]; n]; n],
+            opening: vec![vec![]; n],
+            opening_arsenals: vec![Arsenal::empty(); n],
+            opening_rng: StdRng::from_entropy(),
+            orders: vec![],
+            maneuvers: vec![0; n],
+            miss_epochs: vec![vec![[[[0; 10]; 10]; 2]; n]; n],
+            skips: vec![false; n],
+            fog: vec![vec![vec![]; n]; n],
+            delayed: vec![vec![]; n],
+            events: vec![],
+            started_at: None,
+            finished_at: None,
+            rng: StdRng::from_entropy(),
+            ai_rng: StdRng::from_entropy(),
+            rules,
+        };
+        // everyone starts on the next enemy in turn order
+        for i in 0..n {
+            let player = PlayerID(i);
+            game.aims[i] = game.enemies(player)[0];
+        }
+        game
+    }
+
+    // a game that hasn't been played yet with the same fleets as this one,
+    // laid out as they were at the start
+    pub fn with_same_fleets(&self) -> GameState {
+        let mut game = GameState::initialize(self.rules.clone());
+        for i in 0..self.players.len() {
+            for pos in self.cells() {
+                match self.ships[i].get_cell_value(pos) {
+                    BoardCell::Island => {
+                        game.ships[i].set_cell(pos, BoardCell::Island);
+                        for view in &mut game.attacks {
+                            view[i].set_cell(pos, BoardCell::Island);
+                        }
+                    }
+                    BoardCell::Mine => game.ships[i].set_cell(pos, BoardCell::Mine),
+                    _ => {}
+                }
+            }
+            let fleet = if self.opening[i].is_empty() {
+                self.players[i].ships()
+            } else {
+                &self.opening[i]
+            };
+            for ship in fleet {
+                let mut ship = ship.clone();
+                ship.status = ShipStatus::Undamaged;
+                ship.damage = vec![false; ship.position.len()];
+                ship.cooldown = 0;
+                for pos in ship.positions() {
+                    game.ships[i].set_cell(pos, BoardCell::Ship);
+                }
+                game.players[i].add_ship(ship);
+            }
+        }
+        game.opening = self.opening.clone();
+        game.arsenals = self.opening_arsenals.clone();
+        game.opening_arsenals = self.opening_arsenals.clone();
+        game.rng = self.opening_rng.clone();
+        game.opening_rng = self.opening_rng.clone();
+        game.status = GameStatus::InProgress;
+        game.started_at = Some(Instant::now());
+        game
+    }
+
+    // every order given so far in turn order, with whose board it was aimed at
+    pub fn orders(&self) -> &[LoggedOrder] {
+        &self.orders
+    }
+
+    pub fn rules(&self) -> &Rules {
+        &self.rules
+    }
+
+    // every cell of a board, on each layer the rules have
+    fn cells(&self) -> Vec<Position> {
+        self.rules
+            .layers()
+            .into_iter()
+            .flat_map(|depth| ai::all_cells().map(move |p| p.at(depth)))
+            .collect()
+    }
+
+    // whether the two players are on the same side
+    pub fn allies(&self, a: PlayerID, b: PlayerID) -> bool {
+        self.rules.team(a) == self.rules.team(b)
+    }
+
+    // the player and their teammates, in or out of the game
+    fn team(&self, player: PlayerID) -> Vec<PlayerID> {
+        (0..self.players.len())
+            .map(PlayerID)
+            .filter(|&p| self.allies(player, p))
+            .collect()
+    }
+
+    // the players of the other sides still in the game, in turn order
+    pub fn enemies(&self, player: PlayerID) -> Vec<PlayerID> {
+        (0..self.players.len())
+            .map(PlayerID)
+            .filter(|&p| !self.allies(player, p) && !self.out[p.index()])
+            .collect()
+    }
+
+    pub fn is_out(&self, player: PlayerID) -> bool {
+        self.out[player.index()]
+    }
+
+    // whose board `player` fires at
+    pub fn aim(&self, player: PlayerID) -> PlayerID {
+        self.aims[player.index()]
+    }
+
+    // point `player` at the board of `target`, false if that isn't an enemy
+    // still in the game
+    pub fn set_aim(&mut self, player: PlayerID, target: PlayerID) -> bool {
+        if !self.enemies(player).contains(&target) {
+            return false;
+        }
+        self.aims[player.index()] = target;
+        true
+    }
+
+    // whether the side of `player` won the game
+    pub fn won(&self, player: PlayerID) -> bool {
+        matches!(self.status, GameStatus::Complete(winner) if self.allies(winner, player))
+    }
+
+    // time from the start until the game was won, or until now while playing
+    pub fn duration(&self) -> Duration {
+        match (self.started_at, self.finished_at) {
+            (Some(start), Some(end)) => end - start,
+            (Some(start), None) => start.elapsed(),
+            _ => Duration::ZERO,
+        }
+    }
+
+    // hand over everything that happened since the last call
+    pub fn drain_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn place_randomly(&mut self, player: PlayerID) {
+        for kind in self.rules.fleet.clone() {
+            let mut placed = false;
+
+            while !placed {
+                let ship = self.random_ship(kind);
+                placed = self.try_place_ship(&player, ship);
+            }
+        }
+    }
+
+    // lay out the fleet of `player` the way the strategy prefers, by trying a
+    // number of random legal spots for each ship and keeping the best one
+    pub fn place_fleet(&mut self, player: PlayerID, placement: Placement, history: &ShotHistory) {
+        if placement == Placement::Random {
+            self.place_randomly(player);
+            return;
+        }
+        for kind in self.rules.fleet.clone() {
+            let placed = self.players[player.index()].all_ship_positions();
+            let mut best: Option<(f64, Ship)> = None;
+            let mut candidates = 0;
+            while candidates < PLACEMENT_CANDIDATES {
+                let ship = self.random_ship(kind);
+                if !self.fits(player, &ship) {
+                    continue;
+                }
+                candidates += 1;
+                let score = placement.score(&ship.positions(), &placed, history, self.rules.grid);
+                if best.as_ref().is_none_or(|(s, _)| score > *s) {
+                    best = Some((score, ship));
+                }
+            }
+            if let Some((_, ship)) = best {
+                self.place_ship(player, ship);
+            }
+        }
+    }
+
+    // a ship of the kind laid from a random cell in a random direction, it
+    // may not fit there
+    fn random_ship(&mut self, kind: ShipKind) -> Ship {
+        let pos = Position::random(&mut self.rng).at(self.rules.depth_of(kind));
+        let mut ship = Ship::new(kind);
+        match self.rules.grid {
+            Grid::Square => ship.place_at(pos, Transform::random(&mut self.rng)),
+            Grid::Hex => ship.place_along(pos, self.rng.gen_range(0..hex::AXES.len())),
+        };
+        ship
+    }
+
+    // put the islands on the same cells of every board and every layer, some
+    // with a reef running off to a neighbouring cell
+    fn raise_islands(&mut self) {
+        let mut raised = 0;
+        while raised < ISLANDS {
+            let pos = Position::random(&mut self.rng);
+            let mut cells = vec![pos];
+            if self.rng.gen_bool(0.5) {
+                let next = self.rules.grid.neighbours(pos);
+                cells.push(next[self.rng.gen_range(0..next.len())]);
+            }
+            if cells
+                .iter()
+                .any(|&p| self.ships[0].get_cell_value(p) != BoardCell::Empty)
+            {
+                continue;
+            }
+            for p in cells {
+                for depth in self.rules.layers() {
+                    for board in &mut self.ships {
+                        board.set_cell(p.at(depth), BoardCell::Island);
+                    }
+                    for board in self.attacks.iter_mut().flatten() {
+                        board.set_cell(p.at(depth), BoardCell::Island);
+                    }
+                }
+            }
+            raised += 1;
+        }
+    }
+
+    // lay the mines of `player` on open water once the fleet is placed; a
+    // strategy other than random puts them where the opponents fired most
+    // often in earlier games
+    fn lay_mines(&mut self, player: PlayerID, placement: Placement, history: &ShotHistory) {
+        let index = player.index();
+        for _ in 0..MINES {
+            let free: Vec<Position> = ai::all_cells()
+                .filter(|&p| self.ships[index].get_cell_value(p) == BoardCell::Empty)
+                .collect();
+            let rng = &mut self.rng;
+            let best = free.into_iter().max_by_key(|p| {
+                let fired = match placement {
+                    Placement::Random => 0,
+                    _ => history[p.x as usize][p.y as usize],
+                };
+                (fired, rng.gen::<u32>())
+            });
+            if let Some(pos) = best {
+                self.ships[index].set_cell(pos, BoardCell::Mine);
+            }
+        }
+    }
+
+    // whether the ship is on the board and clear of the rest of the fleet
+    fn fits(&self, player: PlayerID, ship: &Ship) -> bool {
+        let p = &self.players[player.index()];
+        self.fits_among(player, ship, p.all_ship_positions())
+    }
+
+    // whether the ship is on the board of `player`, off its islands and mines
+    // and clear of the cells of other ships
+    fn fits_among(&self, player: PlayerID, ship: &Ship, others: Vec<Position>) -> bool {
+        let board = &self.ships[player.index()];
+        let out_of_bounds = ship.positions().into_iter().any(|p| p.x > 9 || p.y > 9);
+        if out_of_bounds {
+            return false;
+        }
+        let terrain = ship
+            .positions()
+            .into_iter()
+            .any(|p| matches!(board.get_cell_value(p), BoardCell::Island | BoardCell::Mine));
+        let overlaps = if self.rules.ships_may_touch {
+            ship.intersects(&others)
+        } else {
+            // the cells around every placed ship are off limits too
+            let taken: Vec<Position> = others
+                .into_iter()
+                .flat_map(|q| self.rules.grid.around(q))
+                .collect();
+            ship.intersects(&taken)
+        };
+
+        !overlaps && !terrain
+    }
+
+    fn place_ship(&mut self, player: PlayerID, ship: Ship) {
+        let index = player.index();
+        let (p, b) = (&mut self.players[index], &mut self.ships[index]);
+        p.add_ship(ship);
+        for pos in p.all_ship_positions() {
+            b.set_cell(pos, BoardCell::Ship)
+        }
+    }
+
+    fn try_place_ship(&mut self, player: &PlayerID, ship: Ship) -> bool {
+        let valid = self.fits(*player, &ship);
+
+        if valid {
+            self.place_ship(*player, ship);
+        }
+
+        valid
+    }
+
+    // whether `player` has a ship cell left that hasn't been hit
+    fn afloat(&self, player: PlayerID) -> bool {
+        self.cells()
+            .into_iter()
+            .any(|p| self.ships[player.index()].get_cell_value(p) == BoardCell::Ship)
+    }
+
+    // check winner by looking at the fleets remaining, the game is over once
+    // they are all on one side
+    pub fn check_winner(&mut self) {
+        // a finished game is only reported once
+        if let GameStatus::Complete(_) = self.status {
+            return;
+        }
+        let remaining: Vec<PlayerID> = (0..self.players.len())
+            .map(PlayerID)
+            .filter(|&p| self.afloat(p))
+            .collect();
+        if let Some(&first) = remaining.first() {
+            if remaining.iter().all(|&p| self.allies(first, p)) {
+                self.status = GameStatus::Complete(first);
+            }
+        }
+        if let GameStatus::Complete(winner) = self.status {
+            self.finished_at = Some(Instant::now());
+            // the fog lifts at the end
+            for i in 0..self.players.len() {
+                for fog in &mut self.fog[i] {
+                    fog.clear();
+                }
+                let delayed = std::mem::take(&mut self.delayed[i]);
+                self.events.extend(delayed);
+            }
+            self.events.push(GameEvent::Victory(winner));
+        }
+    }
+
+    // players whose last ship went down drop out, and whoever was aiming at
+    // them turns to another enemy
+    fn eliminate(&mut self) {
+        for i in 0..self.players.len() {
+            if !self.out[i] && !self.afloat(PlayerID(i)) {
+                self.out[i] = true;
+                self.events.push(GameEvent::Eliminated(PlayerID(i)));
+            }
+        }
+        for i in 0..self.players.len() {
+            if self.out[self.aims[i].index()] {
+                if let Some(&next) = self.enemies(PlayerID(i)).first() {
+                    self.aims[i] = next;
+                }
+            }
+        }
+    }
+
+    // the next player still in the game after `player`, passing over anyone
+    // who set off a mine and sits this turn out
+    fn next_turn(&mut self, player: PlayerID) -> PlayerID {
+        let n = self.players.len();
+        for step in 1..n {
+            let next = (player.index() + step) % n;
+            if self.out[next] || std::mem::take(&mut self.skips[next]) {
+                continue;
+            }
+            return PlayerID(next);
+        }
+        player
+    }
+
+    fn do_attack(attack_board: &mut Board, target_board: &mut Board, attack_at: Position) -> bool {
+        let target_cell = target_board.get_cell_value(attack_at);
+        // a wreck another player already hit is a hit too
+        if matches!(target_cell, BoardCell::Ship | BoardCell::DamagedShip) {
+            attack_board.set_cell(attack_at, BoardCell::SuccessfulAttack);
+            target_board.set_cell(attack_at, BoardCell::DamagedShip);
+            true
+        } else {
+            attack_board.set_cell(attack_at, BoardCell::FailedAttack);
+            false
+        }
+    }
+
+    // teammates see each other's shots, pass on what `player` now knows of
+    // the cell, and when it was a miss how often the target had manoeuvred
+    fn share(&mut self, player: PlayerID, target: PlayerID, pos: Position) {
+        let (a, t) = (player.index(), target.index());
+        let cell = self.attacks[a][t].get_cell_value(pos);
+        for mate in self.team(player) {
+            self.attacks[mate.index()][t].set_cell(pos, cell);
+            if cell == BoardCell::FailedAttack {
+                self.miss_epochs[mate.index()][t][pos.depth.index()][pos.x as usize]
+                    [pos.y as usize] = self.maneuvers[t];
+            }
+        }
+    }
+
+    // hit a single cell of the aimed at board without ending the turn
+    fn strike(&mut self, player: PlayerID, pos: Position, weapon: Weapon) -> bool {
+        let target = self.aim(player);
+        let (attacker, t) = (player.index(), target.index());
+        self.last_attacks[attacker] = Some((target, pos));
+        let mine = self.ships[t].get_cell_value(pos) == BoardCell::Mine;
+        let hit = GameState::do_attack(&mut self.attacks[attacker][t], &mut self.ships[t], pos);
+        // under fog the shooter hears about it after their next turn
+        let reported = self.events.len();
+        if mine {
+            self.attacks[attacker][t].set_cell(pos, BoardCell::Mine);
+        }
+        self.share(player, target, pos);
+        self.events.push(GameEvent::Attack {
+            attacker: player,
+            target,
+            pos,
+            hit,
+        });
+        if mine {
+            self.set_off_mine(player, pos);
+        }
+        let mut sunk = None;
+        if hit {
+            self.players[t].update_ship_at(pos, &self.ships[t]);
+            if let Some(ship) = self.players[t].ship_at(pos).filter(|s| s.is_sunk()) {
+                sunk = Some(ship.kind);
+                self.events.push(GameEvent::Sunk {
+                    attacker: player,
+                    owner: target,
+                    kind: ship.kind,
+                    positions: ship.positions(),
+                });
+            }
+        }
+        if self.rules.fog {
+            let news = self.events.split_off(reported);
+            self.delayed[attacker].extend(news.into_iter().map(|event| match event {
+                GameEvent::Attack {
+                    attacker,
+                    target,
+                    pos,
+                    hit,
+                } => GameEvent::Reported {
+                    attacker,
+                    target,
+                    pos,
+                    hit,
+                },
+                event => event,
+            }));
+            self.fog[attacker][t].push(pos);
+            self.events.push(GameEvent::Fired {
+                attacker: player,
+                target,
+                pos,
+            });
+        }
+        let turn = self.shots.iter().filter(|s| s.player == player).count() as u32 + 1;
+        self.shots.push(Shot {
+            player,
+            target,
+            pos,
+            hit,
+            sunk,
+            turn,
+            weapon,
+        });
+        hit
+    }
+
+    // `player` fired at a mine on the board they aim at and pays for it; only
+    // `strike` calls it, so under fog the event waits with the shot's
+    fn set_off_mine(&mut self, player: PlayerID, pos: Position) {
+        let target = self.aim(player);
+        let (attacker, t) = (player.index(), target.index());
+        let revealed = match self.rules.mine_penalty {
+            MinePenalty::Skip => {
+                self.skips[attacker] = true;
+                None
+            }
+            MinePenalty::Reveal => {
+                // a cell of their fleet the mine's owner hasn't hit or been shown
+                let hidden: Vec<Position> = self
+                    .cells()
+                    .into_iter()
+                    .filter(|&p| self.ships[attacker].get_cell_value(p) == BoardCell::Ship)
+                    .filter(|&p| !self.contacts[t][attacker].iter().any(|c| c.area == [p]))
+                    .collect();
+                if hidden.is_empty() {
+                    None
+                } else {
+                    let cell = hidden[self.rng.gen_range(0..hidden.len())];
+                    self.contacts[t][attacker].push(Contact {
+                        centre: cell,
+                        area: vec![cell],
+                    });
+                    Some(cell)
+                }
+            }
+        };
+        self.events.push(GameEvent::Mine {
+            attacker: player,
+            target,
+            pos,
+            revealed,
+        });
+    }
+
+    // sweep the area with sonar, cells of a clear area are marked as misses
+    fn scan(&mut self, player: PlayerID, centre: Position, area: Vec<Position>) {
+        let target = self.aim(player);
+        let (attacker, t) = (player.index(), target.index());
+        let found = area
+            .iter()
+            .any(|&p| self.ships[t].get_cell_value(p) == BoardCell::Ship);
+        if !found {
+            for &pos in &area {
+                if self.untried(player, target, pos) {
+                    self.attacks[attacker][t].set_cell(pos, BoardCell::FailedAttack);
+                    self.share(player, target, pos);
+                }
+            }
+        } else {
+            self.contacts[attacker][t].push(Contact {
+                centre,
+                area: area.clone(),
+            });
+        }
+        let event = GameEvent::Sonar {
+            scanner: player,
+            target,
+            area,
+            found,
+        };
+        // under fog the scanner hears back after their next turn, like a shot
+        if self.rules.fog {
+            self.delayed[attacker].push(event);
+        } else {
+            self.events.push(event);
+        }
+    }
+
+    pub fn arsenal(&self, player: PlayerID) -> &Arsenal {
+        &self.arsenals[player.index()]
+    }
+
+    // the sonar contacts of `player` and their teammates on the board of
+    // `target` where no ship has been hit yet
+    pub fn contacts(&self, player: PlayerID, target: PlayerID) -> Vec<&Contact> {
+        let view = self.seen(player, target);
+        self.team(player)
+            .into_iter()
+            .flat_map(|mate| &self.contacts[mate.index()][target.index()])
+            .filter(|c| {
+                c.area
+                    .iter()
+                    .all(|&p| view.get_cell_value(p) != BoardCell::SuccessfulAttack)
+            })
+            .collect()
+    }
+
+    // how many times `target` manoeuvred since the miss of `player` at `pos`,
+    // None if it isn't a miss they were told about
+    fn miss_age(&self, player: PlayerID, target: PlayerID, pos: Position) -> Option<u32> {
+        let (attacker, t) = (player.index(), target.index());
+        if self.seen(player, target).get_cell_value(pos) != BoardCell::FailedAttack {
+            return None;
+        }
+        let epoch =
+            self.miss_epochs[attacker][t][pos.depth.index()][pos.x as usize][pos.y as usize];
+        Some(self.maneuvers[t] - epoch)
+    }
+
+    // the misses of `player` on the board of `target` a ship may have moved
+    // onto since
+    pub fn stale_misses(&self, player: PlayerID, target: PlayerID) -> Vec<Position> {
+        self.cells()
+            .into_iter()
+            .filter(|&p| self.miss_age(player, target, p).is_some_and(|age| age > 0))
+            .collect()
+    }
+
+    // whether `player` may still fire at the cell of `target`, stale misses
+    // count with a mobile fleet
+    fn untried(&self, player: PlayerID, target: PlayerID, pos: Position) -> bool {
+        let view = &self.attacks[player.index()][target.index()];
+        match view.get_cell_value(pos) {
+            BoardCell::Empty | BoardCell::Repaired => true,
+            BoardCell::FailedAttack => {
+                self.rules.mobile_fleet
+                    && self
+                        .miss_age(player, target, pos)
+                        .is_some_and(|age| age > 0)
+            }
+            _ => false,
+        }
+    }
+
+    // whether `player` has the weapon and it would reach an untried cell of
+    // the board they aim at, on a layer it can get to
+    pub fn can_fire(&self, player: PlayerID, order: &Order) -> bool {
+        if !self.available(player, order.weapon) {
+            return false;
+        }
+        if self.rules.depth && !order.weapon.reaches(order.target.depth) {
+            return false;
+        }
+        let target = self.aim(player);
+        match order.weapon {
+            Weapon::Move => self.moved_ship(player, order.target).is_some(),
+            Weapon::Maneuver => self.maneuvered(player, order.target).is_some(),
+            Weapon::Repair => self.repairable(player, order.target).is_some(),
+            _ => order
+                .footprint()
+                .iter()
+                .any(|&p| self.untried(player, target, p)),
+        }
+    }
+
+    // the afloat ship of `player` whose ability is `weapon` and ready to use
+    fn ready_ship(&self, player: PlayerID, weapon: Weapon) -> Option<usize> {
+        let owner = &self.players[player.index()];
+        if !self.rules.ship_abilities {
+            return None;
+        }
+        owner
+            .ships()
+            .iter()
+            .position(|s| s.kind.ability() == Some(weapon) && !s.is_sunk() && s.cooldown == 0)
+    }
+
+    // whether `player` can use the weapon or ability this turn
+    pub fn available(&self, player: PlayerID, weapon: Weapon) -> bool {
+        match weapon {
+            Weapon::Ping | Weapon::Recon | Weapon::DoubleShot | Weapon::Move => {
+                self.ready_ship(player, weapon).is_some()
+            }
+            Weapon::Maneuver => {
+                let owner = &self.players[player.index()];
+                self.rules.mobile_fleet
+                    && owner
+                        .ships()
+                        .iter()
+                        .any(|s| s.status == ShipStatus::Undamaged)
+            }
+            _ => self.arsenal(player).available(weapon),
+        }
+    }
+
+    // everything `player` can use this turn besides the shell
+    pub fn ready(&self, player: PlayerID) -> Vec<Weapon> {
+        Weapon::all()
+            .into_iter()
+            .filter(|&w| w != Weapon::Shell && self.available(player, w))
+            .collect()
+    }
+
+    // the next weapon after `weapon` that `player` can use, the shell at the latest
+    pub fn next_weapon(&self, player: PlayerID, weapon: Weapon) -> Weapon {
+        let all = Weapon::all();
+        let start = all.iter().position(|&w| w == weapon).unwrap_or(0);
+        (1..=all.len())
+            .map(|i| all[(start + i) % all.len()])
+            .find(|&w| self.available(player, w))
+            .unwrap_or(Weapon::Shell)
+    }
+
+    // where the movable ship of `player` ends up when it steps onto `target`,
+    // a cell next to it; only an undamaged ship moves, and never onto a cell an
+    // opponent has tried, so what they know stays true
+    pub fn moved_ship(&self, player: PlayerID, target: Position) -> Option<Vec<Position>> {
+        let index = self.ready_ship(player, Weapon::Move)?;
+        let owner = &self.players[player.index()];
+        let ship = &owner.ships()[index];
+        if ship.status != ShipStatus::Undamaged || target.overlaps(&ship.position) {
+            return None;
+        }
+        let (dx, dy) = ship.position.iter().find_map(|p| {
+            let (dx, dy) = (target.x as i8 - p.x as i8, target.y as i8 - p.y as i8);
+            (dx.abs() + dy.abs() == 1).then_some((dx, dy))
+        })?;
+        let mut moved = ship.clone();
+        moved.position = ship
+            .position
+            .iter()
+            .map(|p| Position {
+                x: (p.x as i8 + dx) as u8,
+                y: (p.y as i8 + dy) as u8,
+                ..*p
+            })
+            .collect();
+        let others: Vec<Position> = owner
+            .ships()
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .flat_map(|(_, s)| s.positions())
+            .collect();
+        let tried = moved.position.iter().any(|&p| {
+            p.x > 9
+                || p.y > 9
+                || self
+                    .attacks
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| i != player.index())
+                    .any(|(_, view)| view[player.index()].get_cell_value(p) != BoardCell::Empty)
+        });
+        if tried || !self.fits_among(player, &moved, others) {
+            return None;
+        }
+        Some(moved.position)
+    }
+
+    // the undamaged ship of `player` that `target` points at and where it ends
+    // up: a cell of the ship turns it about its centre, the cell past either
+    // end moves it a cell along its axis; a bent ship moves towards any cell
+    // next to it
+    pub fn maneuvered(&self, player: PlayerID, target: Position) -> Option<(usize, Vec<Position>)> {
+        if !self.available(player, Weapon::Maneuver) {
+            return None;
+        }
+        let owner = &self.players[player.index()];
+        owner.ships().iter().enumerate().find_map(|(index, ship)| {
+            if ship.status != ShipStatus::Undamaged {
+                return None;
+            }
+            let cells = &ship.position;
+            let (first, last) = (*cells.first()?, *cells.last()?);
+            let horizontal = cells.len() < 2 || first.y == last.y;
+            let (dx, dy) = if horizontal { (1i8, 0i8) } else { (0, 1) };
+            let at = |p: Position, i: i8, (dx, dy): (i8, i8)| Position {
+                x: (p.x as i8 + i * dx) as u8,
+                y: (p.y as i8 + i * dy) as u8,
+                ..p
+            };
+            let centre = cells[cells.len() / 2];
+            let moved: Vec<Position> = if target.overlaps(cells) && ship.kind.straight() {
+                // turned about the middle cell onto the other axis
+                let half = (cells.len() / 2) as i8;
+                (0..cells.len() as i8)
+                    .map(|i| at(centre, i - half, (dy, dx)))
+                    .collect()
+            } else if target.overlaps(cells) {
+                // a quarter turn clockwise about the middle cell
+                cells
+                    .iter()
+                    .map(|p| Position {
+                        x: (centre.x as i8 - (p.y as i8 - centre.y as i8)) as u8,
+                        y: (centre.y as i8 + (p.x as i8 - centre.x as i8)) as u8,
+                        ..*p
+                    })
+                    .collect()
+            } else if !ship.kind.straight() {
+                let step = cells.iter().find_map(|p| {
+                    let (dx, dy) = (target.x as i8 - p.x as i8, target.y as i8 - p.y as i8);
+                    (dx.abs() + dy.abs() == 1).then_some((dx, dy))
+                })?;
+                cells.iter().map(|&p| at(p, 1, step)).collect()
+            } else if target == at(first, -1, (dx, dy)) {
+                cells.iter().map(|&p| at(p, -1, (dx, dy))).collect()
+            } else if target == at(last, 1, (dx, dy)) {
+                cells.iter().map(|&p| at(p, 1, (dx, dy))).collect()
+            } else {
+                return None;
+            };
+            let mut candidate = ship.clone();
+            candidate.position = moved;
+            let others: Vec<Position> = owner
+                .ships()
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| i != index)
+                .flat_map(|(_, s)| s.positions())
+                .collect();
+            self.fits_among(player, &candidate, others)
+                .then_some((index, candidate.position))
+        })
+    }
+
+    // the ship of `player` with a hit at `target` that is still afloat, so the
+    // hit can be repaired
+    pub fn repairable(&self, player: PlayerID, target: Position) -> Option<usize> {
+        let owner = &self.players[player.index()];
+        owner
+            .ships()
+            .iter()
+            .position(|s| !s.is_sunk() && target.overlaps(&s.damaged_cells()))
+    }
+
+    // mend the hit, whoever hit the cell is shown it is a ship again
+    fn repair(&mut self, player: PlayerID, target: Position) {
+        let Some(index) = self.repairable(player, target) else {
+            return;
+        };
+        let owner = player.index();
+        self.ships[owner].set_cell(target, BoardCell::Ship);
+        for view in &mut self.attacks {
+            if view[owner].get_cell_value(target) == BoardCell::SuccessfulAttack {
+                view[owner].set_cell(target, BoardCell::Repaired);
+            }
+        }
+        self.players[owner].update_ship_at(target, &self.ships[owner]);
+        self.events.push(GameEvent::Repaired {
+            owner: player,
+            kind: self.players[owner].ships()[index].kind,
+            pos: target,
+        });
+    }
+
+    fn move_ship(&mut self, player: PlayerID, target: Position) {
+        let Some(cells) = self.moved_ship(player, target) else {
+            return;
+        };
+        let Some(index) = self.ready_ship(player, Weapon::Move) else {
+            return;
+        };
+        self.relocate(player, index, cells);
+    }
+
+    // manoeuvre a ship, the opponents' misses from before may be wrong now
+    fn maneuver(&mut self, player: PlayerID, target: Position) {
+        let Some((index, cells)) = self.maneuvered(player, target) else {
+            return;
+        };
+        self.maneuvers[player.index()] += 1;
+        self.relocate(player, index, cells);
+    }
+
+    // put a ship of `player` onto other cells of their board
+    fn relocate(&mut self, player: PlayerID, index: usize, cells: Vec<Position>) {
+        let owner = player.index();
+        let ship = &mut self.players[owner].ships[index];
+        for &p in &ship.position {
+            self.ships[owner].set_cell(p, BoardCell::Empty);
+        }
+        for &p in &cells {
+            self.ships[owner].set_cell(p, BoardCell::Ship);
+        }
+        ship.position = cells;
+        self.events.push(GameEvent::Moved {
+            owner: player,
+            kind: ship.kind,
+        });
+    }
+
+    // carry out the order as the player's turn against the board they aim at,
+    // returns whether a ship was hit; an order that isn't allowed right now
+    // changes nothing
+    pub fn fire(&mut self, player: PlayerID, order: Order) -> Result<bool, String> {
+        if self.status != GameStatus::InProgress {
+            return Err(String::from("the game is over"));
+        }
+        if self.turn != player {
+            return Err(format!("it is not {}'s turn", player.name()));
+        }
+        if !self.can_fire(player, &order) {
+            return Err(format!(
+                "{} can't use the {} on {}",
+                player.name(),
+                order.weapon.name(),
+                order.target.label()
+            ));
+        }
+        let target = self.aim(player);
+        let (attacker, t) = (player.index(), target.index());
+        self.orders.push((player, target, order));
+        self.arsenals[attacker].spend(order.weapon);
+        // what the last turn did is reported once this one is over
+        for fog in &mut self.fog[attacker] {
+            fog.clear();
+        }
+        let news = std::mem::take(&mut self.delayed[attacker]);
+        let weapon = order.weapon;
+        // the ability's ship rests, the others get one turn closer to theirs
+        let user = self.ready_ship(player, weapon);
+        for ship in &mut self.players[attacker].ships {
+            ship.cooldown = ship.cooldown.saturating_sub(1);
+        }
+        let mut hit = false;
+        match weapon {
+            Weapon::Shell | Weapon::DoubleShot => hit = self.strike(player, order.target, weapon),
+            Weapon::Sonar | Weapon::Ping | Weapon::Recon => {
+                self.scan(player, order.target, order.footprint())
+            }
+            Weapon::Move => self.move_ship(player, order.target),
+            Weapon::Maneuver => self.maneuver(player, order.target),
+            Weapon::Repair => self.repair(player, order.target),
+            Weapon::Torpedo => {
+                // misses on the way are passed over, any ship stops it
+                for pos in order.footprint() {
+                    let ship = matches!(
+                        self.ships[t].get_cell_value(pos),
+                        BoardCell::Ship | BoardCell::DamagedShip
+                    );
+                    if self.untried(player, target, pos) {
+                        hit |= self.strike(player, pos, weapon);
+                    }
+                    if ship {
+                        break;
+                    }
+                }
+            }
+            Weapon::Airstrike | Weapon::ClusterBomb => {
+                for pos in order.footprint() {
+                    if self.untried(player, target, pos) {
+                        hit |= self.strike(player, pos, weapon);
+                    }
+                }
+            }
+        }
+        if let Some(index) = user {
+            self.players[attacker].ships[index].cooldown = weapon.cooldown();
+        }
+        self.events.extend(news);
+        self.eliminate();
+        // the double shot leaves the player another one, and a player who set
+        // off a mine loses theirs
+        if weapon != Weapon::DoubleShot {
+            self.turn = self.next_turn(player);
+            self.events.push(GameEvent::TurnChanged(self.turn));
+        }
+        Ok(hit)
+    }
+
+    // let the computer pick its target and shot for the given player
+    pub fn ai_attack(&mut self, player: PlayerID, difficulty: Difficulty) {
+        let attacker = player.index();
+        let target = self.choose_enemy(player);
+        self.aims[attacker] = target;
+        // the AI sees one layer at a time, moved up to the surface, and its
+        // shot goes back down to the layer it picked
+        let depth = self.choose_layer(player, target);
+        // the view borrows the game, so draw from a copy of the generator and
+        // put it back afterwards
+        let mut rng = self.ai_rng.clone();
+        let (view, sunk, afloat) = self.knowledge(player, target, depth);
+        // the easy level sticks to the shell
+        let mut ready = match difficulty {
+            Difficulty::Easy => vec![],
+            _ => self.ready(player),
+        };
+        if self.rules.depth {
+            ready.retain(|w| w.reaches(depth));
+        }
+        let contacts: Vec<Contact> = self
+            .contacts(player, target)
+            .into_iter()
+            .filter(|c| c.centre.depth == depth)
+            .map(|c| Contact {
+                centre: c.centre.at(Depth::Surface),
+                area: c.area.iter().map(|p| p.at(Depth::Surface)).collect(),
+            })
+            .collect();
+        let contacts: Vec<&Contact> = contacts.iter().collect();
+        let special = self
+            .repair_order(player, &ready)
+            .or_else(|| {
+                ai::choose_special(&view, &sunk, &afloat, &ready, &contacts).map(|order| Order {
+                    target: order.target.at(depth),
+                    ..order
+                })
+            })
+            .or_else(|| self.evasion(player, &ready))
+            .filter(|order| self.can_fire(player, order));
+        let mut order = special.unwrap_or_else(|| {
+            let prior = &self.priors[attacker];
+            Order::shell(
+                ai::choose_target(difficulty, &view, &sunk, &afloat, prior, &mut rng).at(depth),
+            )
+        });
+        // a bonus shot costs nothing but the wait
+        if order.weapon == Weapon::Shell && ready.contains(&Weapon::DoubleShot) {
+            order.weapon = Weapon::DoubleShot;
+        }
+        self.ai_rng = rng;
+        self.fire(player, order)
+            .expect("the computer only gives orders it may give");
+    }
+
+    // the enemy whose board holds the likeliest shot for `player`, staying on
+    // the one aimed at unless another is better
+    fn choose_enemy(&self, player: PlayerID) -> PlayerID {
+        let aimed = self.aim(player);
+        let enemies = self.enemies(player);
+        if enemies.len() < 2 {
+            return aimed;
+        }
+        let best_shot = |target: PlayerID| {
+            self.rules
+                .layers()
+                .into_iter()
+                .filter_map(|depth| self.best_shot(player, target, depth))
+                .fold(0.0, f64::max)
+        };
+        let mut best = (aimed, best_shot(aimed));
+        for target in enemies.into_iter().filter(|&e| e != aimed) {
+            let chance = best_shot(target);
+            if chance > best.1 {
+                best = (target, chance);
+            }
+        }
+        best.0
+    }
+
+    // the layer of the board of `target` where `player` has the likeliest shot,
+    // the surface while it is as good as the water below
+    fn choose_layer(&self, player: PlayerID, target: PlayerID) -> Depth {
+        let mut best = (Depth::Surface, None);
+        for depth in self.rules.layers() {
+            let chance = self.best_shot(player, target, depth);
+            if chance > best.1 {
+                best = (depth, chance);
+            }
+        }
+        best.0
+    }
+
+    // the chance of a hit with the best untried cell on one layer of the board
+    // of `target`, None if every cell there was tried
+    fn best_shot(&self, player: PlayerID, target: PlayerID, depth: Depth) -> Option<f64> {
+        let heat = self.heatmap(player, target, depth);
+        ai::all_cells()
+            .filter(|&p| self.untried(player, target, p.at(depth)))
+            .map(|p| heat[p.x as usize][p.y as usize])
+            .reduce(f64::max)
+    }
+
+    // mend a ship that one more hit would sink, the opponent then spends a shot
+    // to get back to where they were
+    fn repair_order(&self, player: PlayerID, ready: &[Weapon]) -> Option<Order> {
+        if !ready.contains(&Weapon::Repair) {
+            return None;
+        }
+        let owner = &self.players[player.index()];
+        let ship = owner
+            .ships()
+            .iter()
+            .find(|s| !s.is_sunk() && s.damaged_cells().len() + 1 == s.positions().len())?;
+        let target = *ship.damaged_cells().last()?;
+        Some(Order {
+            weapon: Weapon::Repair,
+            target,
+            orientation: ShipOrientation::Horizontal,
+        })
+    }
+
+    // move a ship away from where the opponents are likely to shoot, with the
+    // destroyer's ability or a manoeuvre
+    fn evasion(&self, player: PlayerID, ready: &[Weapon]) -> Option<Order> {
+        let owner = &self.players[player.index()];
+        // every order that moves a ship, with the cells before and after
+        let mut options = vec![];
+        for weapon in [Weapon::Move, Weapon::Maneuver] {
+            if !ready.contains(&weapon) {
+                continue;
+            }
+            for ship in owner.ships().iter().filter(|s| !s.is_sunk()) {
+                let cells = ship.positions();
+                let targets = cells
+                    .iter()
+                    .flat_map(|&p| self.rules.grid.neighbours(p))
+                    .chain(cells.iter().copied());
+                for target in targets {
+                    let moved = match weapon {
+                        Weapon::Move => self.moved_ship(player, target),
+                        _ => self
+                            .maneuvered(player, target)
+                            .filter(|(index, _)| owner.ships()[*index].positions() == cells)
+                            .map(|(_, moved)| moved),
+                    };
+                    if let Some(moved) = moved.filter(|m| *m != cells) {
+                        let order = Order {
+                            weapon,
+                            target,
+                            orientation: ShipOrientation::Horizontal,
+                        };
+                        options.push((order, cells.clone(), moved));
+                    }
+                }
+            }
+        }
+        if options.is_empty() {
+            return None;
+        }
+        // a cell is as threatened as the enemy most likely to fire at it thinks
+        let mut threat = [[[0.0; 10]; 10]; 2];
+        for enemy in self.enemies(player) {
+            for depth in self.rules.layers() {
+                let heat = self.heatmap(enemy, player, depth);
+                let layer = &mut threat[depth.index()];
+                for p in ai::all_cells() {
+                    let (x, y) = (p.x as usize, p.y as usize);
+                    layer[x][y] = f64::max(layer[x][y], heat[x][y]);
+                }
+            }
+        }
+        ai::evasion(&threat, &options)
+    }
+
+    // let the AI of `player` use what it knows about the opponent's layouts
+    pub fn set_layout_prior(&mut self, player: PlayerID, prior: LayoutPrior) {
+        self.priors[player.index()] = prior;
+    }
+
+    // the board of `target` as `player` sees it, teammates' shots included;
+    // shots that haven't been reported are pending
+    pub fn seen(&self, player: PlayerID, target: PlayerID) -> Board {
+        let mut view = self.attacks[player.index()][target.index()];
+        for mate in self.team(player) {
+            for &pos in &self.fog[mate.index()][target.index()] {
+                view.set_cell(pos, BoardCell::Pending);
+            }
+        }
+        view
+    }
+
+    // every shot anyone fired at the board of `target`, for showing it after
+    // the game
+    pub fn fired_on(&self, target: PlayerID) -> Board {
+        let mut board = Board::new(self.rules.grid);
+        for (i, view) in self.attacks.iter().enumerate() {
+            if i == target.index() {
+                continue;
+            }
+            for p in self.cells() {
+                if board.get_cell_value(p) == BoardCell::Empty {
+                    board.set_cell(p, view[target.index()].get_cell_value(p));
+                }
+            }
+        }
+        board
+    }
+
+    // the state of a ship of `target` as far as `player` has been told: the
+    // hits they and their teammates saw, a ship with a pending shot on it
+    // isn't known to be sunk
+    pub fn reported_status(&self, player: PlayerID, target: PlayerID, ship: &Ship) -> ShipStatus {
+        let view = self.seen(player, target);
+        let hits = ship
+            .position
+            .iter()
+            .filter(|&&p| view.get_cell_value(p) == BoardCell::SuccessfulAttack)
+            .count();
+        if hits == 0 {
+            ShipStatus::Undamaged
+        } else if hits == ship.position.len() {
+            ShipStatus::Sunk
+        } else {
+            ShipStatus::Damaged
+        }
+    }
+
+    // what `player` knows about the fleet of `target` on one layer, moved up
+    // to the surface: their view of it, the cells of the ships they sank there
+    // and the kinds there still afloat
+    fn knowledge(
+        &self,
+        player: PlayerID,
+        target: PlayerID,
+        depth: Depth,
+    ) -> (Board, Vec<Position>, Vec<ShipKind>) {
+        let ships: Vec<&Ship> = self.players[target.index()]
+            .ships()
+            .iter()
+            .filter(|s| self.rules.depth_of(s.kind) == depth)
+            .collect();
+        let known_sunk = |s: &&&Ship| self.reported_status(player, target, s) == ShipStatus::Sunk;
+        let afloat: Vec<ShipKind> = ships
+            .iter()
+            .filter(|s| !known_sunk(s))
+            .map(|s| s.kind)
+            .collect();
+        let sunk: Vec<Position> = ships
+            .iter()
+            .filter(known_sunk)
+            .flat_map(|s| s.positions())
+            .map(|p| p.at(Depth::Surface))
+            .collect();
+        // misses from many manoeuvres ago say little about where ships are now,
+        // and once every cell was tried the fleet must be on a recent one
+        let mut view = self.seen(player, target).layer(depth);
+        if self.rules.mobile_fleet {
+            let stale: Vec<Position> = self
+                .stale_misses(player, target)
+                .into_iter()
+                .filter(|p| p.depth == depth)
+                .collect();
+            for &pos in &stale {
+                if self
+                    .miss_age(player, target, pos)
+                    .is_some_and(|age| age >= TRUST_MANEUVERS)
+                {
+                    view.set_cell(pos.at(Depth::Surface), BoardCell::Empty);
+                }
+            }
+            let untried = ai::all_cells().any(|p| view.get_cell_value(p) == BoardCell::Empty);
+            if !untried {
+                for pos in stale {
+                    view.set_cell(pos.at(Depth::Surface), BoardCell::Empty);
+                }
+            }
+        }
+        (view, sunk, afloat)
+    }
+
+    // the best next shot for `player` on a layer of the board they aim at,
+    // worked out only from what they can see
+    pub fn hint(&self, player: PlayerID, depth: Depth) -> Option<Hint> {
+        let (view, sunk, afloat) = self.knowledge(player, self.aim(player), depth);
+        ai::hint(&view, &sunk, &afloat).map(|hint| Hint {
+            pos: hint.pos.at(depth),
+            ..hint
+        })
+    }
+
+    // how likely each cell on a layer of the board of `target` holds a ship, as
+    // far as `player` can tell, indexed [x][y]
+    pub fn heatmap(&self, player: PlayerID, target: PlayerID, depth: Depth) -> [[f64; 10]; 10] {
+        let (view, sunk, afloat) = self.knowledge(player, target, depth);
+        ai::heatmap(&view, &sunk, &afloat)
+    }
+
+    // lay out every fleet with a strategy per player and begin, `histories`
+    // holds the shots fired at each player's board in earlier games
+    pub fn start_with(&mut self, placements: &[Placement], histories: &[&ShotHistory]) {
+        self.status = GameStatus::InProgress;
+        self.started_at = Some(Instant::now());
+        // the special weapons and the repairs the rules hand out
+        let stock: Vec<Weapon> = Weapon::all()
+            .into_iter()
+            .filter(|&w| match w {
+                Weapon::Repair => self.rules.repairs,
+                _ => self.rules.special_weapons,
+            })
+            .collect();
+        self.arsenals = vec![Arsenal::stocked(&stock); self.players.len()];
+        if self.rules.terrain {
+            self.raise_islands();
+        }
+        for i in 0..self.players.len() {
+            self.place_fleet(PlayerID(i), placements[i], histories[i]);
+        }
+        if self.rules.terrain {
+            for i in 0..self.players.len() {
+                self.lay_mines(PlayerID(i), placements[i], histories[i]);
+            }
+        }
+        self.opening = self.players.iter().map(|p| p.ships().clone()).collect();
+        self.opening_arsenals = self.arsenals.clone();
+        self.opening_rng = self.rng.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_are_labelled_by_column_letter_and_row_number() {
+        let at = |x, y| Position {
+            x,
+            y,
+            depth: Depth::Surface,
+        };
+        assert_eq!(at(0, 0).label(), "A1");
+        assert_eq!(at(4, 6).label(), "E7");
+        assert_eq!(at(9, 9).label(), "J10");
+    }
+
+    #[test]
+    fn the_last_shot_of_each_player_is_remembered() {
+        let mut game = GameState::initialize(Rules::new());
+        game.start_with(&[Placement::Random; 2], &[&[[0; 10]; 10]; 2]);
+        assert_eq!(game.last_attacks, vec![None, None]);
+        let pos = Position {
+            x: 3,
+            y: 8,
+            depth: Depth::Surface,
+        };
+        game.fire(PlayerID::P1, Order::shell(pos)).unwrap();
+        assert_eq!(game.last_attacks[0], Some((PlayerID::P2, pos)));
+        game.ai_attack(PlayerID::P2, Difficulty::Normal);
+        let answer = game.shots.last().unwrap();
+        assert_eq!(game.last_attacks[1], Some((PlayerID::P1, answer.pos)));
+        assert_eq!(game.last_attacks[0], Some((PlayerID::P2, pos)));
+    }
+
+    // an L: three down and one to the right at the bottom
+    const L: [(i8, i8); 4] = [(0, 0), (0, 1), (0, 2), (1, 2)];
+
+    fn sorted(mut cells: Vec<(i8, i8)>) -> Vec<(i8, i8)> {
+        cells.sort();
+        cells
+    }
+
+    #[test]
+    fn the_identity_keeps_the_shape() {
+        let identity = Transform {
+            turns: 0,
+            mirrored: false,
+        };
+        assert_eq!(identity.apply(&L), L.to_vec());
+    }
+
+    #[test]
+    fn a_quarter_turn_is_clockwise() {
+        let turn = Transform {
+            turns: 1,
+            mirrored: false,
+        };
+        assert_eq!(sorted(turn.apply(&L)), vec![(0, 0), (0, 1), (1, 0), (2, 0)]);
+        let line = [(0, 0), (1, 0), (2, 0)];
+        assert_eq!(
+            Transform::from(ShipOrientation::Vertical).apply(&line),
+            vec![(0, 0), (0, 1), (0, 2)]
+        );
+    }
+
+    #[test]
+    fn mirroring_flips_left_and_right() {
+        let mirror = Transform {
+            turns: 0,
+            mirrored: true,
+        };
+        assert_eq!(
+            sorted(mirror.apply(&L)),
+            vec![(0, 2), (1, 0), (1, 1), (1, 2)]
+        );
+    }
+
+    #[test]
+    fn an_l_has_eight_distinct_layouts_moved_to_the_origin() {
+        let mut layouts: Vec<Vec<(i8, i8)>> = Transform::all()
+            .iter()
+            .map(|t| sorted(t.apply(&L)))
+            .collect();
+        for cells in &layouts {
+            assert_eq!(cells.iter().map(|c| c.0).min(), Some(0));
+            assert_eq!(cells.iter().map(|c| c.1).min(), Some(0));
+        }
+        layouts.sort();
+        layouts.dedup();
+        assert_eq!(layouts.len(), 8);
+    }
+
+    #[test]
+    fn four_quarter_turns_come_back_round() {
+        let turns = |n| Transform {
+            turns: n,
+            mirrored: true,
+        };
+        assert_eq!(turns(0).apply(&L), turns(4).apply(&L));
+    }
+
+    #[test]
+    fn old_misses_on_a_mobile_fleet_are_searched_again() {
+        let rules = Rules {
+            mobile_fleet: true,
+            ..Rules::new()
+        };
+        let mut game = GameState::initialize(rules);
+        game.start_with(&[Placement::Random; 2], &[&[[0; 10]; 10]; 2]);
+        let water = game
+            .cells()
+            .into_iter()
+            .find(|&p| game.ships[1].get_cell_value(p) == BoardCell::Empty)
+            .expect("the fleet doesn't fill the board");
+        game.fire(PlayerID::P1, Order::shell(water)).unwrap();
+        let heat = |game: &GameState| {
+            game.heatmap(PlayerID::P1, PlayerID::P2, Depth::Surface)[water.x as usize]
+                [water.y as usize]
+        };
+        assert!(game.stale_misses(PlayerID::P1, PlayerID::P2).is_empty());
+        assert!(!game.can_fire(PlayerID::P1, &Order::shell(water)));
+        assert_eq!(heat(&game), 0.0);
+
+        // the target manoeuvred since, the miss may be worth another shot
+        game.maneuvers[1] += 1;
+        assert_eq!(game.stale_misses(PlayerID::P1, PlayerID::P2), vec![water]);
+        assert!(game.can_fire(PlayerID::P1, &Order::shell(water)));
+        assert_eq!(heat(&game), 0.0);
+        // and after enough of them the AI stops trusting it
+        game.maneuvers[1] += TRUST_MANEUVERS;
+        assert!(heat(&game) > 0.0);
+    }
+
+    #[test]
+    fn orders_out_of_turn_or_on_tried_cells_change_nothing() {
+        let mut game = GameState::initialize(Rules::new());
+        game.start_with(&[Placement::Random; 2], &[&[[0; 10]; 10]; 2]);
+        let order = Order::shell(Position {
+            x: 0,
+            y: 0,
+            depth: Depth::Surface,
+        });
+        assert!(game.fire(PlayerID::P2, order).is_err());
+        assert!(game.fire(PlayerID::P1, order).is_ok());
+        game.turn = PlayerID::P1;
+        let shots = game.shots.len();
+        assert!(game.fire(PlayerID::P1, order).is_err());
+        assert_eq!(game.shots.len(), shots);
+        assert_eq!(game.orders().len(), 1);
+    }
+
+    #[test]
+    fn under_fog_mines_and_sonar_are_reported_a_turn_later() {
+        let rules = Rules {
+            fog: true,
+            special_weapons: true,
+            ..Rules::new()
+        };
+        let mut game = GameState::initialize(rules);
+        game.start_with(&[Placement::Random; 2], &[&[[0; 10]; 10]; 2]);
+        let water: Vec<Position> = game
+            .cells()
+            .into_iter()
+            .filter(|&p| game.ships[1].get_cell_value(p) == BoardCell::Empty)
+            .collect();
+        game.ships[1].set_cell(water[0], BoardCell::Mine);
+        game.drain_events();
+
+        let mine = |e: &GameEvent| matches!(e, GameEvent::Mine { .. });
+        let sonar = |e: &GameEvent| matches!(e, GameEvent::Sonar { .. });
+        game.fire(PlayerID::P1, Order::shell(water[0])).unwrap();
+        assert!(!game.drain_events().iter().any(mine));
+        game.turn = PlayerID::P1;
+        game.skips[0] = false;
+        let scan = Order {
+            weapon: Weapon::Sonar,
+            target: water[1],
+            orientation: ShipOrientation::Horizontal,
+        };
+        game.fire(PlayerID::P1, scan).unwrap();
+        let events = game.drain_events();
+        assert!(events.iter().any(mine));
+        assert!(!events.iter().any(sonar));
+        game.turn = PlayerID::P1;
+        let far = *water.last().expect("there is water");
+        game.fire(PlayerID::P1, Order::shell(far)).unwrap();
+        assert!(game.drain_events().iter().any(sonar));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::game::Position;
+
+// a cell of a hex board in axial coordinates: q runs along a row and r down
+// the rows, so every straight line of cells keeps q, r or q + r fixed; the
+// board stores its cells in rows of ten with the odd rows half a cell to the
+// right, which is what `Position` holds
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Hex {
+    pub q: i8,
+    pub r: i8,
+}
+
+// the six cells around a hex, going round from the east
+const DIRECTIONS: [(i8, i8); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+// the three ways a ship can lie: along the row, down to the right and down to
+// the left
+pub const AXES: [(i8, i8); 3] = [(1, 0), (0, 1), (-1, 1)];
+
+impl Hex {
+    pub fn from_position(pos: Position) -> Self {
+        let (x, y) = (pos.x as i8, pos.y as i8);
+        Hex {
+            q: x - (y - (y & 1)) / 2,
+            r: y,
+        }
+    }
+
+    // the cell of the board on the layer of `like`, past the edge a
+    // coordinate wraps around above 9 like anywhere else on the board
+    pub fn to_position(self, like: Position) -> Position {
+        Position {
+            x: (self.q + (self.r - (self.r & 1)) / 2) as u8,
+            y: self.r as u8,
+            ..like
+        }
+    }
+
+    fn step(self, (dq, dr): (i8, i8), n: i8) -> Hex {
+        Hex {
+            q: self.q + n * dq,
+            r: self.r + n * dr,
+        }
+    }
+}
+
+fn on_board(pos: &Position) -> bool {
+    pos.x < 10 && pos.y < 10
+}
+
+// the cells sharing an edge with `pos` that are on the board
+pub fn neighbours(pos: Position) -> Vec<Position> {
+    let hex = Hex::from_position(pos);
+    DIRECTIONS
+        .iter()
+        .map(|&d| hex.step(d, 1).to_position(pos))
+        .filter(on_board)
+        .collect()
+}
+
+// `length` cells from `start` along one of the axes, including the ones past
+// the edge
+pub fn line(start: Position, axis: usize, length: u8) -> Vec<Position> {
+    let hex = Hex::from_position(start);
+    (0..length as i8)
+        .map(|i| hex.step(AXES[axis], i).to_position(start))
+        .collect()
+}
+
+// the cell after `to` on the line from `from` through it, `from` and `to`
+// being neighbours
+pub fn beyond(from: Position, to: Position) -> Position {
+    let (a, b) = (Hex::from_position(from), Hex::from_position(to));
+    b.step((b.q - a.q, b.r - a.r), 1).to_position(to)
+}
+
+// two cells out of three, laid out so that every pair of neighbours has at
+// least one of them; a ship of two or more always covers one
+pub fn parity(pos: Position) -> bool {
+    let hex = Hex::from_position(pos);
+    (hex.q - hex.r).rem_euclid(3) != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Depth;
+
+    fn at(x: u8, y: u8) -> Position {
+        Position {
+            x,
+            y,
+            depth: Depth::Surface,
+        }
+    }
+
+    #[test]
+    fn odd_rows_are_shifted_half_a_cell() {
+        assert_eq!(Hex::from_position(at(3, 0)), Hex { q: 3, r: 0 });
+        assert_eq!(Hex::from_position(at(3, 1)), Hex { q: 3, r: 1 });
+        assert_eq!(Hex::from_position(at(3, 2)), Hex { q: 2, r: 2 });
+        assert_eq!(Hex::from_position(at(0, 9)), Hex { q: -4, r: 9 });
+    }
+
+    #[test]
+    fn positions_survive_the_round_trip() {
+        for x in 0..10 {
+            for y in 0..10 {
+                let pos = at(x, y).at(Depth::Submerged);
+                assert_eq!(Hex::from_position(pos).to_position(pos), pos);
+            }
+        }
+    }
+
+    #[test]
+    fn neighbours_are_on_the_board_and_mutual() {
+        assert_eq!(neighbours(at(4, 4)).len(), 6);
+        assert_eq!(neighbours(at(0, 0)).len(), 2);
+        assert_eq!(neighbours(at(9, 1)).len(), 3);
+        for x in 0..10 {
+            for y in 0..10 {
+                for next in neighbours(at(x, y)) {
+                    assert!(neighbours(next).contains(&at(x, y)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lines_keep_going_the_same_way() {
+        // down to the right from an even row alternates staying and stepping
+        assert_eq!(
+            line(at(2, 2), 1, 4),
+            vec![at(2, 2), at(2, 3), at(3, 4), at(3, 5)]
+        );
+        let cells = line(at(5, 1), 2, 3);
+        assert_eq!(cells, vec![at(5, 1), at(5, 2), at(4, 3)]);
+        assert_eq!(beyond(cells[0], cells[1]), cells[2]);
+    }
+
+    #[test]
+    fn every_pair_of_neighbours_has_a_parity_cell() {
+        for x in 0..10 {
+            for y in 0..10 {
+                if !parity(at(x, y)) {
+                    assert!(neighbours(at(x, y)).into_iter().all(parity));
+                }
+            }
+        }
+    }
+}
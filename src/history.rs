@@ -0,0 +1,197 @@
+use crate::ai::Difficulty;
+use crate::game::{GameState, GameStatus, LoggedOrder, PlayerID};
+use crate::weapons::Order;
+
+// one turn against the computer: the player's shot and the answer to it
+struct Step {
+    // the game as it was before the turn, random generator included
+    before: GameState,
+    order: Order,
+    difficulty: Difficulty,
+}
+
+// the turns played so far, which can be taken back when the game allows it
+pub struct History {
+    done: Vec<Step>,
+    undone: Vec<Step>,
+    takebacks: bool,
+}
+
+impl History {
+    pub fn new(takebacks: bool) -> Self {
+        History {
+            done: vec![],
+            undone: vec![],
+            takebacks,
+        }
+    }
+
+    pub fn takebacks(&self) -> bool {
+        self.takebacks
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.takebacks && !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.takebacks && !self.undone.is_empty()
+    }
+
+    fn apply(game: &mut GameState, order: Order, difficulty: Difficulty) -> Result<(), String> {
+        game.fire(PlayerID::P1, order)?;
+        game.check_winner();
+        // a double shot keeps the turn, the computer may take more than one
+        // too, and with more players every other seat is one of its fleets
+        while game.turn != PlayerID::P1 && game.status == GameStatus::InProgress {
+            game.ai_attack(game.turn, difficulty);
+            game.check_winner();
+        }
+        Ok(())
+    }
+
+    // carry out the order and let the computer answer, as one step; an order
+    // the game doesn't allow leaves both the game and the history as they were
+    pub fn play(
+        &mut self,
+        game: &mut GameState,
+        order: Order,
+        difficulty: Difficulty,
+    ) -> Result<(), String> {
+        let before = game.clone();
+        History::apply(game, order, difficulty)?;
+        self.done.push(Step {
+            before,
+            order,
+            difficulty,
+        });
+        self.undone.clear();
+        Ok(())
+    }
+
+    // put the game back to before the last step, returns false if there was none
+    pub fn undo(&mut self, game: &mut GameState) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        let step = self.done.pop().expect("checked above");
+        *game = step.before.clone();
+        self.undone.push(step);
+        true
+    }
+
+    // play the last undone step again, the restored generator makes the
+    // computer answer the same way
+    pub fn redo(&mut self, game: &mut GameState) -> bool {
+        if !self.can_redo() {
+            return false;
+        }
+        let step = self.undone.pop().expect("checked above");
+        *game = step.before.clone();
+        History::apply(game, step.order, step.difficulty).expect("it was played before");
+        self.done.push(step);
+        true
+    }
+
+    // the game before the first shot
+    pub fn start(&self) -> Option<&GameState> {
+        self.done.first().map(|step| &step.before)
+    }
+
+    // the game before the first shot and every order of every player that
+    // led to the current one, for replays
+    pub fn replay(&self) -> Option<(GameState, Vec<LoggedOrder>)> {
+        let start = self.start()?.clone();
+        let mut game = start.clone();
+        for step in &self.done {
+            History::apply(&mut game, step.order, step.difficulty).expect("it was played before");
+        }
+        Some((start, game.orders().to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::{self, Placement};
+    use crate::game::Rules;
+
+    fn new_game() -> GameState {
+        let mut game = GameState::initialize(Rules::new());
+        game.start_with(&[Placement::Random; 2], &[&[[0; 10]; 10]; 2]);
+        game
+    }
+
+    // the player's turns going down the board a cell at a time
+    fn play_turns(history: &mut History, game: &mut GameState, turns: usize) {
+        for _ in 0..turns {
+            let order = ai::all_cells()
+                .map(Order::shell)
+                .find(|order| game.can_fire(PlayerID::P1, order))
+                .expect("a cell is left");
+            history.play(game, order, Difficulty::Normal).unwrap();
+        }
+    }
+
+    fn same(a: &GameState, b: &GameState) {
+        assert_eq!(a.shots, b.shots);
+        assert_eq!(a.orders(), b.orders());
+        assert_eq!(a.attacks, b.attacks);
+        assert_eq!(a.turn, b.turn);
+        assert_eq!(a.status, b.status);
+    }
+
+    #[test]
+    fn undoing_and_redoing_every_turn_ends_where_it_started() {
+        let mut game = new_game();
+        let mut history = History::new(true);
+        play_turns(&mut history, &mut game, 12);
+        let played = game.clone();
+        while history.undo(&mut game) {}
+        assert!(game.shots.is_empty());
+        while history.redo(&mut game) {}
+        same(&game, &played);
+    }
+
+    #[test]
+    fn the_replay_matches_the_game_after_takebacks() {
+        let mut game = new_game();
+        let mut history = History::new(true);
+        play_turns(&mut history, &mut game, 8);
+        for _ in 0..3 {
+            history.undo(&mut game);
+        }
+        play_turns(&mut history, &mut game, 5);
+        assert!(!history.can_redo());
+
+        let (start, orders) = history.replay().unwrap();
+        assert_eq!(orders, game.orders());
+        let mut replayed = start;
+        for &(player, target, order) in &orders {
+            replayed.set_aim(player, target);
+            replayed.fire(player, order).unwrap();
+            replayed.check_winner();
+        }
+        same(&replayed, &game);
+    }
+
+    #[test]
+    fn illegal_orders_leave_no_step() {
+        let mut game = new_game();
+        let mut history = History::new(true);
+        play_turns(&mut history, &mut game, 1);
+        let first = game.orders()[0].2;
+        assert!(history.play(&mut game, first, Difficulty::Normal).is_err());
+        assert!(history.undo(&mut game));
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn ranked_games_cant_be_taken_back() {
+        let mut game = new_game();
+        let mut history = History::new(false);
+        play_turns(&mut history, &mut game, 2);
+        assert!(!history.undo(&mut game));
+        assert_eq!(history.start().map(|s| s.shots.len()), Some(0));
+    }
+}
@@ -1,540 +1,424 @@
+mod ai;
+mod analysis;
+mod animation;
+mod game;
+mod hex;
+mod history;
+mod profile;
+mod rating;
+mod render;
+mod replay;
+mod settings;
+mod sim;
+mod stats;
+mod theme;
 mod utils;
+mod weapons;
 // use cursive::view::Margins;
 // use cursive::views::{Dialog, EditView, LinearLayout, Panel, TextView};
 // use cursive::{immut2, Cursive};
-use rand::Rng;
-use std::ops::Not;
-use std::time::SystemTime;
-use utils::{draw_block, draw_circle};
+use ai::{Difficulty, Hint, LayoutPrior, Placement, ShotHistory};
+use analysis::Analysis;
+use animation::Animator;
+use game::{Depth, GameState, GameStatus, Grid, PlayerID, Position, ShipOrientation};
+use history::History;
+use profile::{bot_name, GameResult, ProfileError, ProfileStore};
+use render::{
+    board_cell_at, clicked_button, render, render_analysis, render_arsenal, render_error,
+    render_game_over, render_heatmap, render_hint, render_leaderboard, render_menu, render_notice,
+    render_replay, render_stats, small_board_at, MenuButton, ENEMY_OFFSET_X, ENEMY_OFFSET_Y,
+    OWN_OFFSET_X, OWN_OFFSET_Y,
+};
+use replay::Replay;
+use settings::{Settings, SettingsWatcher};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use theme::{Theme, DEFAULT_FONT};
+use weapons::{Order, Weapon};
 extern crate find_folder;
 use piston_window::*;
 
-const BACK_COLOR: [f32; 4] = [0.2, 0.2, 0.2, 1.0];
-const OWN_OFFSET_X: i32 = 3;
-const OWN_OFFSET_Y: i32 = 3;
-const ENEMY_OFFSET_X: i32 = 18;
-const ENEMY_OFFSET_Y: i32 = 3;
-const BLOCK_SIZE: f64 = 25.0;
-// set the colors representing different statesx`
-
-#[derive(PartialEq, Debug, Copy, Clone)]
-struct Position {
-    x: u8,
-    y: u8,
-}
-
-impl Position {
-    pub fn random() -> Self {
-        let mut r = rand::thread_rng();
-        Position {
-            x: r.gen_range(0..10),
-            y: r.gen_range(0..10),
-        }
-    }
+// fn game_over( winner: PlayerID) {
+//     let winner_text = match winner {
+//         PlayerID::P1 => "Player 1 Wins!",
+//         PlayerID::P2 => "Player 2 Wins!",
+//     };
+//     // s.pop_layer();
+//     // s.add_layer(
+//     //     Dialog::text(winner_text)
+//     //         .title("Game Over")
+//     //         .button("OK", |s| choose_game_type(s)),
+//     // )
+// }
 
-    pub fn overlaps(&self, positions: &Vec<Position>) -> bool {
-        positions.contains(self)
-    }
+// which part of the interface is showing
+enum Screen {
+    Menu,
+    Playing,
+    GameOver,
+    Replay(Box<Replay>),
+    Stats,
+    Leaderboard,
+    Analysis,
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-enum ShipOrientation {
-    Horizontal,
-    Vertical,
+// what the buttons on the different screens do
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+    Play,
+    ChangeDifficulty,
+    ToggleRanked,
+    NextProfile,
+    NewProfile,
+    ShowStats,
+    ShowLeaderboard,
+    ForgetLayouts,
+    Quit,
+    ViewReplay,
+    ShowAnalysis,
+    ExportAnalysis,
+    ExportHeatmap,
+    Rematch,
+    MainMenu,
+    BackToResults,
 }
 
-impl ShipOrientation {
-    pub fn random() -> Self {
-        let mut r = rand::thread_rng();
-        if r.gen_bool(0.5) {
-            ShipOrientation::Horizontal
-        } else {
-            ShipOrientation::Vertical
-        }
-    }
+fn menu_buttons(settings: &Settings, profile: &str) -> Vec<MenuButton<Action>> {
+    let mode = if settings.ranked { "ranked" } else { "casual" };
+    let items = [
+        (Action::Play, String::from("Play")),
+        (
+            Action::ChangeDifficulty,
+            format!("Difficulty: {}", settings.difficulty.name()),
+        ),
+        (Action::ToggleRanked, format!("Mode: {}", mode)),
+        (Action::NextProfile, format!("Profile: {}", profile)),
+        (Action::NewProfile, String::from("New profile")),
+        (Action::ShowStats, String::from("Statistics")),
+        (Action::ShowLeaderboard, String::from("Leaderboard")),
+        (Action::Quit, String::from("Quit")),
+    ];
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, (action, label))| {
+            MenuButton::new(*action, label, 15.0, 6.0 + i as f64 * 1.5, 8.0)
+        })
+        .collect()
 }
 
-impl Not for ShipOrientation {
-    type Output = Self;
-    fn not(self) -> Self::Output {
-        match self {
-            ShipOrientation::Horizontal => ShipOrientation::Vertical,
-            ShipOrientation::Vertical => ShipOrientation::Horizontal,
-        }
-    }
-}
-#[derive(PartialEq, Debug, Copy, Clone)]
-enum ShipStatus {
-    Undamaged,
+fn game_over_buttons() -> Vec<MenuButton<Action>> {
+    vec![
+        MenuButton::new(Action::ViewReplay, "View replay", 3.0, 17.6, 6.0),
+        MenuButton::new(Action::ShowAnalysis, "Analysis", 10.0, 17.6, 6.0),
+        MenuButton::new(Action::Rematch, "Rematch", 17.0, 17.6, 6.0),
+        MenuButton::new(Action::MainMenu, "Main menu", 24.0, 17.6, 6.0),
+    ]
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-enum ShipKind {
-    Patrol,
-    Submarine,
-    Destroyer,
-    Battleship,
-    Carrier,
+fn analysis_buttons() -> Vec<MenuButton<Action>> {
+    vec![
+        MenuButton::new(Action::BackToResults, "Back", 3.0, 17.6, 6.0),
+        MenuButton::new(Action::ExportAnalysis, "Export", 10.0, 17.6, 6.0),
+        MenuButton::new(Action::MainMenu, "Main menu", 17.0, 17.6, 6.0),
+    ]
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-enum BoardCell {
-    Empty,
-    Ship,
-    DamagedShip,
-    FailedAttack,
-    SuccessfulAttack,
+// a new file in the data directory, named after the current time
+fn export_path(dir: &str, prefix: &str, extension: &str) -> PathBuf {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    profile::data_dir()
+        .join(dir)
+        .join(format!("{}-{}.{}", prefix, seconds, extension))
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-struct Board {
-    cells: [[BoardCell; 10]; 10],
+fn replay_buttons() -> Vec<MenuButton<Action>> {
+    vec![
+        MenuButton::new(Action::BackToResults, "Back", 3.0, 17.6, 6.0),
+        MenuButton::new(Action::MainMenu, "Main menu", 10.0, 17.6, 6.0),
+    ]
 }
 
-impl Board {
-    pub fn new() -> Self {
-        Board {
-            cells: [[BoardCell::Empty; 10]; 10],
-        }
-    }
-
-    pub fn render_board(&self, con: &Context, g: &mut G2d, x_offset: i32, y_offset: i32) {
-        // for row in 0..10 {
-        //     for col in 0..10 {
-        //         draw_block(color::GREEN, row + x_offset, col + y_offset, con, g);
-        //     }
-        // }
-        for row in 0i32..10 {
-            for col in 0i32..10 {
-                match self.cells[row as usize][col as usize] {
-                    BoardCell::Empty => {
-                        draw_block(color::CYAN, row + x_offset, col + y_offset, con, g)
-                    }
-                    BoardCell::Ship => {
-                        draw_block(color::LIME, row + x_offset, col + y_offset, con, g)
-                    }
-                    BoardCell::DamagedShip => {
-                        draw_block(color::RED, row + x_offset, col + y_offset, con, g)
-                    }
-                    BoardCell::FailedAttack => {
-                        draw_block(color::NAVY, row + x_offset, col + y_offset, con, g)
-                    }
-                    BoardCell::SuccessfulAttack => {
-                        draw_block(color::RED, row + x_offset, col + y_offset, con, g)
-                    }
-                }
-            }
-        }
-    }
-
-    pub fn set_cell(&mut self, pos: Position, value: BoardCell) {
-        self.cells[pos.x as usize][pos.y as usize] = value;
-    }
-
-    pub fn get_cell_value(&self, pos: Position) -> BoardCell {
-        self.cells[pos.x as usize][pos.y as usize].clone()
-    }
+fn stats_buttons() -> Vec<MenuButton<Action>> {
+    vec![
+        MenuButton::new(Action::MainMenu, "Back", 3.0, 17.6, 6.0),
+        MenuButton::new(Action::ForgetLayouts, "Forget my fleets", 10.0, 17.6, 8.0),
+    ]
 }
 
-#[derive(PartialEq, Debug, Clone)]
-struct Ship {
-    kind: ShipKind,
-    size: u8,
-    position: Vec<Position>,
-    status: ShipStatus,
+fn leaderboard_buttons() -> Vec<MenuButton<Action>> {
+    vec![MenuButton::new(Action::MainMenu, "Back", 3.0, 17.6, 6.0)]
 }
 
-impl Ship {
-    pub fn new(kind: ShipKind) -> Self {
-        match kind {
-            ShipKind::Patrol => Self {
-                kind,
-                size: 2,
-                position: vec![],
-                status: ShipStatus::Undamaged,
-            },
-            ShipKind::Submarine => Self {
-                kind,
-                size: 3,
-                position: vec![],
-                status: ShipStatus::Undamaged,
-            },
-            ShipKind::Destroyer => Self {
-                kind,
-                size: 3,
-                position: vec![],
-                status: ShipStatus::Undamaged,
-            },
-            ShipKind::Battleship => Self {
-                kind,
-                size: 4,
-                position: vec![],
-                status: ShipStatus::Undamaged,
-            },
-            ShipKind::Carrier => Self {
-                kind,
-                size: 5,
-                position: vec![],
-                status: ShipStatus::Undamaged,
-            },
-        }
-    }
-
-    pub fn place_at(&mut self, pos: Position, orient: ShipOrientation) -> &mut Self {
-        self.position.clear();
-        self.position = (0..self.size)
-            .into_iter()
-            .map(|i| match orient {
-                ShipOrientation::Horizontal => Position {
-                    x: pos.x + i,
-                    y: pos.y,
-                },
-                ShipOrientation::Vertical => Position {
-                    x: pos.x,
-                    y: pos.y + i,
-                },
-            })
-            .collect();
-
-        self
+// the buttons shown on a screen, the one with keyboard focus marked
+fn screen_buttons(
+    screen: &Screen,
+    settings: &Settings,
+    profile: &str,
+    focus: usize,
+) -> Vec<MenuButton<Action>> {
+    let mut buttons = match screen {
+        Screen::Menu => menu_buttons(settings, profile),
+        Screen::Playing => vec![],
+        Screen::GameOver => game_over_buttons(),
+        Screen::Analysis => analysis_buttons(),
+        Screen::Replay(_) => replay_buttons(),
+        Screen::Stats => stats_buttons(),
+        Screen::Leaderboard => leaderboard_buttons(),
+    };
+    if let Some(button) = buttons.get_mut(focus) {
+        button.focused = true;
     }
+    buttons
+}
 
-    pub fn positions(&self) -> Vec<Position> {
-        self.position.clone()
-    }
-    pub fn intersects(&self, positions: &Vec<Position>) -> bool {
-        self.position.iter().any(|&p| p.overlaps(positions))
+// the cell under the mouse the weapon is aimed at, moving or repairing a ship
+// points at our own board
+fn aimed_cell(weapon: Weapon, mouse: [f64; 2], grid: Grid) -> Option<Position> {
+    if weapon.own_board() {
+        board_cell_at(mouse, OWN_OFFSET_X, OWN_OFFSET_Y, grid)
+    } else {
+        board_cell_at(mouse, ENEMY_OFFSET_X, ENEMY_OFFSET_Y, grid)
     }
 }
 
-struct Player {
-    ships: Vec<Ship>,
+// what the adaptive AI knows about the profile's fleets
+fn learned_layouts(store: &Option<ProfileStore>, name: &str) -> LayoutPrior {
+    store
+        .as_ref()
+        .and_then(|s| s.get(name))
+        .map(|p| p.layout.clone())
+        .unwrap_or_default()
 }
 
-impl Player {
-    pub fn new() -> Self {
-        Player { ships: vec![] }
-    }
+fn learned_shots(store: &Option<ProfileStore>, name: &str) -> ShotHistory {
+    store
+        .as_ref()
+        .and_then(|s| s.get(name))
+        .map(|p| p.fired_at)
+        .unwrap_or([[0; 10]; 10])
+}
 
-    pub fn all_ship_positions(&self) -> Vec<Position> {
-        self.ships()
-            .into_iter()
-            .flat_map(|s| s.positions())
-            .collect()
+// `fired_at` is where the player shot in earlier games, for adaptive placement,
+// and `layouts` where they put their ships, for adaptive targeting; the game
+// keeps the difficulty it started with even if the menu changes it
+fn new_game(
+    settings: &Settings,
+    animator: &mut Animator,
+    fired_at: &ShotHistory,
+    layouts: LayoutPrior,
+) -> (GameState, History, Difficulty) {
+    let mut game = GameState::initialize(settings.rules.clone());
+    // every other seat is the computer
+    let seats = settings.rules.players;
+    for i in 1..seats {
+        game.set_layout_prior(PlayerID(i), layouts.clone());
     }
+    let mut placements = vec![settings.placement_for(settings.difficulty); seats];
+    placements[0] = Placement::Random;
+    let mut histories = vec![fired_at; seats];
+    histories[0] = &[[0; 10]; 10];
+    game.start_with(&placements, &histories);
+    animator.clear();
+    // shots can only be taken back in casual games
+    (game, History::new(!settings.ranked), settings.difficulty)
+}
 
-    pub fn add_ship(&mut self, ship: Ship) {
-        self.ships.push(ship)
+// add the game to the player's career and the computer's record, the ratings
+// only count ranked one-on-one games; a game that isn't over is lost
+fn record_game(
+    store: &mut ProfileStore,
+    game: &GameState,
+    history: &History,
+    (profile, opponent): (&str, &str),
+) -> Result<(), ProfileError> {
+    let result = GameResult::from_game(game, PlayerID::P1, opponent);
+    store.record(profile, false, &result);
+    let duel = game.players.len() == 2;
+    if duel {
+        let bot_result = GameResult {
+            won: !result.won,
+            ..GameResult::from_game(game, PlayerID::P2, profile)
+        };
+        store.record(opponent, true, &bot_result);
     }
-
-    pub fn ships(&self) -> &Vec<Ship> {
-        &self.ships
+    // casual games count in the career but not the rating
+    if duel && !history.takebacks() {
+        if result.won {
+            store.rate(profile, opponent);
+        } else {
+            store.rate(opponent, profile);
+        }
     }
+    store.select(profile);
+    store.save()
 }
 
-#[derive(PartialEq)]
-enum GameStatus {
-    NotStarted,
-    InProgress,
-    Complete(PlayerID),
-}
-
-#[derive(PartialEq, Debug, Clone, Copy)]
-enum PlayerID {
-    P1,
-    P2,
+// leaving a ranked game after the first shot counts as a loss, so starting
+// over can't dodge one; the history is emptied so it isn't counted twice
+fn forfeit(
+    store: &mut Option<ProfileStore>,
+    game: &GameState,
+    history: &mut History,
+    (profile, opponent): (&str, &str),
+) -> Result<(), ProfileError> {
+    let left = game.status == GameStatus::InProgress && history.start().is_some();
+    if history.takebacks() || !left {
+        return Ok(());
+    }
+    *history = History::new(false);
+    match store {
+        Some(store) => record_game(store, game, history, (profile, opponent)),
+        None => Ok(()),
+    }
 }
 
-struct GameState {
-    players: Vec<Player>,
-    ships: Vec<Board>,
-    attacks: Vec<Board>,
-    // p1: Player,
-    // p2: Player,
-    // p1_attack_board: Board,
-    // p1_ship_board: Board,
-    // p2_attack_board: Board,
-    // p2_ship_board: Board,
-    status: GameStatus,
+// the glyphs of a font in the assets folder, with a readable error if it is
+// missing or broken
+fn load_font(window: &mut PistonWindow, assets: &Path, font: &str) -> Result<Glyphs, String> {
+    window
+        .load_font(assets.join(font))
+        .map_err(|e| format!("could not load font {}: {}", font, e))
 }
 
-impl GameState {
-    pub fn initialize() -> Self {
-        GameState {
-            players: vec![Player::new(), Player::new()],
-            attacks: vec![Board::new(), Board::new()],
-            ships: vec![Board::new(), Board::new()],
-            status: GameStatus::NotStarted,
+fn main() {
+    let assets = find_folder::Search::ParentsThenKids(3, 3)
+        .for_folder("assets")
+        .unwrap();
+    println!("{:?}", assets);
+    // settings live next to the assets, edits are picked up while running
+    let mut watcher = SettingsWatcher::new(assets.join("settings.toml"));
+    let mut error_message: Option<String> = None;
+    let mut settings = match watcher.load() {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            error_message = Some(e.to_string());
+            Settings::new()
         }
-    }
+    };
 
-    fn place_randomly(&mut self, player: PlayerID) {
-        for kind in vec![
-            ShipKind::Submarine,
-            ShipKind::Patrol,
-            ShipKind::Destroyer,
-            ShipKind::Battleship,
-            ShipKind::Carrier,
-        ] {
-            let mut placed = false;
-
-            while !placed {
-                let p = Position::random();
-                let o = ShipOrientation::random();
-                placed = self.try_place_ship(&player, kind, p, o);
-            }
+    // headless mode, nothing to draw
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first().filter(|a| sim::COMMANDS.contains(&a.as_str())) {
+        if let Err(e) = sim::run(command, &args[1..], &settings) {
+            eprintln!("{}", e);
+            std::process::exit(1);
         }
+        return;
     }
 
-    fn try_place_ship(
-        &mut self,
-        player: &PlayerID,
-        kind: ShipKind,
-        pos: Position,
-        orient: ShipOrientation,
-    ) -> bool {
-        let p = match player {
-            PlayerID::P1 => &mut self.players[0],
-            PlayerID::P2 => &mut self.players[1],
-        };
-
-        let b = match player {
-            PlayerID::P1 => &mut self.ships[0],
-            PlayerID::P2 => &mut self.ships[1],
-        };
-        let mut ship = Ship::new(kind);
-        ship.place_at(pos, orient);
-
-        let out_of_bounds = ship.positions().into_iter().any(|p| p.x > 9 || p.y > 9);
-        let overlaps = ship.intersects(&p.all_ship_positions());
-
-        let valid = !(overlaps || out_of_bounds);
-
-        if valid {
-            p.add_ship(ship);
-            for pos in p.all_ship_positions() {
-                b.set_cell(pos, BoardCell::Ship)
-            }
+    // without a readable store nothing is recorded, so a broken file isn't overwritten
+    let mut store = match ProfileStore::open(&ProfileStore::default_path()) {
+        Ok(store) => Some(store),
+        Err(e) => {
+            eprintln!("{}", e);
+            error_message = Some(e.to_string());
+            None
         }
+    };
+    let mut profile_name = store
+        .as_ref()
+        .and_then(|s| s.last_profile())
+        .unwrap_or("Player")
+        .to_string();
+    // the name of a new profile while it is being typed
+    let mut name_entry: Option<String> = None;
+    // the button picked with the keyboard and the enemy cell aimed at
+    let mut focus = 0;
+    let mut cursor: Option<Position> = None;
+    // the layer of water both boards show, only 3D games go below the surface
+    let mut layer = Depth::Surface;
+    // the suggested shot, until the next one is fired
+    let mut hint: Option<Hint> = None;
+    let mut show_heatmap = false;
+    // the weapon the next shot is fired with and which way it points
+    let mut weapon = Weapon::Shell;
+    let mut aim = ShipOrientation::Horizontal;
+    // a passing message, like where a file was saved
+    let mut notice: Option<String> = None;
+    // the review of the player's shots in the last finished game
+    let mut analysis: Option<Analysis> = None;
+
+    let mut window: PistonWindow = WindowSettings::new("Battleship game", settings.window_size)
+        .exit_on_esc(false)
+        .build()
+        .unwrap();
 
-        valid
-    }
+    let mut mouse = [0.0, 0.0];
+    let themes = Theme::builtin();
+    let mut theme_index = 0;
+    let mut theme = settings.theme.clone();
+    // a theme whose font can't be loaded falls back to the bundled one
+    let mut glyphs = match load_font(&mut window, &assets, &theme.font) {
+        Ok(glyphs) => glyphs,
+        Err(e) => {
+            eprintln!("{}", e);
+            error_message = Some(e);
+            theme.font = String::from(DEFAULT_FONT);
+            load_font(&mut window, &assets, DEFAULT_FONT).unwrap()
+        }
+    };
+    // effects are driven by the events the game reports
+    let mut animator = Animator::new(settings.animation_ms);
+    let (mut game, mut history, mut difficulty) = new_game(
+        &settings,
+        &mut animator,
+        &learned_shots(&store, &profile_name),
+        learned_layouts(&store, &profile_name),
+    );
+    let mut screen = Screen::Menu;
 
-    // check winner by looking at total ships remaining
-    fn check_winner(&mut self) {
-        let mut p1_count = 0;
-        let mut p2_count = 0;
-        // go over p2 board to check whether they are done
-        for x in 0..10 {
-            for y in 0..10 {
-                if self.ships[1].get_cell_value(Position { x, y }) == BoardCell::Ship {
-                    p2_count += 1;
-                }
-                if self.ships[0].get_cell_value(Position { x, y }) == BoardCell::Ship {
-                    p1_count += 1;
+    while let Some(event) = window.next() {
+        if let Some(args) = event.update_args() {
+            animator.update(args.dt);
+            if let Screen::Replay(replay) = &mut screen {
+                let events = replay.update(args.dt);
+                animator.follow(replay.game(), layer);
+                for game_event in events {
+                    animator.handle(&game_event);
                 }
             }
         }
 
-        if p2_count == 0 {
-            self.status = GameStatus::Complete(PlayerID::P1);
-        } else if p1_count == 0 {
-            self.status = GameStatus::Complete(PlayerID::P2);
-        }
-    }
-
-    fn do_attack(
-        attack_board: &mut Board,
-        target_board: &mut Board,
-        attack_at: Position,
-        animations: &mut Vec<Animation>,
-    ) -> bool {
-        let target_cell = target_board.get_cell_value(attack_at);
-        animations.push(Animation {
-            time_remaining: 500.0,
-            position: attack_at,
-        });
-        if target_cell == BoardCell::Ship {
-            attack_board.set_cell(attack_at, BoardCell::SuccessfulAttack);
-            target_board.set_cell(attack_at, BoardCell::DamagedShip);
-            true
-        } else {
-            attack_board.set_cell(attack_at, BoardCell::FailedAttack);
-            false
+        // pick up edits to the settings file, keeping the old ones if it is invalid
+        let mut reload = None;
+        if let Some(args) = event.update_args() {
+            reload = watcher.poll(args.dt);
         }
-    }
-
-    pub fn attack(
-        &mut self,
-        player: PlayerID,
-        pos: Position,
-        animations: &mut Vec<Animation>,
-    ) -> bool {
-        match player {
-            PlayerID::P1 => {
-                GameState::do_attack(&mut self.attacks[0], &mut self.ships[1], pos, animations)
-            }
-            PlayerID::P2 => {
-                GameState::do_attack(&mut self.attacks[1], &mut self.ships[0], pos, animations)
+        if let Some(Button::Keyboard(key)) = event.press_args() {
+            if settings.keys.reload_settings.matches(key) {
+                reload = Some(watcher.load());
             }
         }
-    }
-
-    pub fn randomly_attack(&mut self, player: PlayerID, animations: &mut Vec<Animation>) {
-        let mut random_pos = Position::random();
-        // check if random position is taken
-        match player {
-            PlayerID::P1 => {
-                // check and only allow attacks on empty cell
-                while self.attacks[0].get_cell_value(random_pos) != BoardCell::Empty {
-                    random_pos = Position::random();
+        match reload {
+            Some(Ok(new_settings)) => {
+                notice = Some(format!("reloaded {}", watcher.path().display()));
+                error_message = None;
+                if new_settings.theme.font != theme.font {
+                    match load_font(&mut window, &assets, &new_settings.theme.font) {
+                        Ok(new_glyphs) => glyphs = new_glyphs,
+                        Err(e) => error_message = Some(e),
+                    }
                 }
-                self.attack(player, random_pos, animations);
-            }
-            PlayerID::P2 => {
-                while self.attacks[1].get_cell_value(random_pos) != BoardCell::Empty {
-                    random_pos = Position::random();
+                if error_message.is_none() {
+                    theme = new_settings.theme.clone();
                 }
-                self.attack(player, random_pos, animations);
+                if new_settings.window_size != settings.window_size {
+                    window.set_size(new_settings.window_size);
+                }
+                animator.set_duration(new_settings.animation_ms);
+                settings = new_settings;
+            }
+            Some(Err(e)) => {
+                eprintln!("{}", e);
+                error_message = Some(e.to_string());
             }
+            None => {}
         }
-    }
-
-    pub fn start(&mut self) {
-        self.status = GameStatus::InProgress;
-        self.place_randomly(PlayerID::P1);
-        self.place_randomly(PlayerID::P2);
-    }
-}
-
-// fn game_over( winner: PlayerID) {
-//     let winner_text = match winner {
-//         PlayerID::P1 => "Player 1 Wins!",
-//         PlayerID::P2 => "Player 2 Wins!",
-//     };
-//     // s.pop_layer();
-//     // s.add_layer(
-//     //     Dialog::text(winner_text)
-//     //         .title("Game Over")
-//     //         .button("OK", |s| choose_game_type(s)),
-//     // )
-// }
-
-// helper method to render a game state
-fn render(con: &Context, g: &mut G2d, glyphs: &mut Glyphs, game: &mut GameState) {
-    // draw the grid
-
-    game.ships[0].render_board(con, g, OWN_OFFSET_X, OWN_OFFSET_Y);
-    game.attacks[0].render_board(con, g, ENEMY_OFFSET_X, ENEMY_OFFSET_Y);
-    // render text for the boards
-    let mut transform = con.transform.trans(BLOCK_SIZE * 5.5, BLOCK_SIZE * 16.0);
-    text::Text::new_color(color::GRAY, 20)
-        .draw("Your board", glyphs, &con.draw_state, transform, g)
-        .unwrap();
-    transform = con.transform.trans(BLOCK_SIZE * 20.5, BLOCK_SIZE * 16.0);
-    text::Text::new_color(color::GRAY, 20)
-        .draw("Enemy board", glyphs, &con.draw_state, transform, g)
-        .unwrap();
-    transform = con.transform.trans(BLOCK_SIZE * 11.0, BLOCK_SIZE * 2.0);
-    text::Text::new_color(color::WHITE, 32)
-        .draw("Battle ship game", glyphs, &con.draw_state, transform, g)
-        .unwrap();
-    transform = con.transform.trans(BLOCK_SIZE * 2.0, BLOCK_SIZE * 18.0);
-    // paint the text
-    text::Text::new_color(color::WHITE, 15)
-        .draw(
-            "* Click on enemy board's grid to attack. Red means hit Blue means miss.",
-            glyphs,
-            &con.draw_state,
-            transform,
-            g,
-        )
-        .unwrap();
-}
-
-// render the animations for dropping attack
-fn render_animations(
-    con: &Context,
-    g: &mut G2d,
-    animations: &mut Vec<Animation>,
-    offset_x: i32,
-    offset_y: i32,
-) {
-    for &animation in animations.clone().iter() {
-        let color = [0.0, 0.0, 0.0, (500.0 - animation.time_remaining) / 500.0];
-        draw_circle(
-            color,
-            animation.position.x as i32 + offset_x,
-            animation.position.y as i32 + offset_y,
-            con,
-            g,
-        );
-    }
-}
-
-fn render_winning_screen(con: &Context, g: &mut G2d, glyphs: &mut Glyphs, winner: PlayerID) {
-    let message = match winner {
-        PlayerID::P1 => "You won the game! :)",
-        PlayerID::P2 => "You lost the game :(",
-    };
-    let mut transform = con.transform.trans(BLOCK_SIZE * 9.0, BLOCK_SIZE * 8.0);
-    text::Text::new_color(color::WHITE, 30)
-        .draw(message, glyphs, &con.draw_state, transform, g)
-        .unwrap();
-    transform = con.transform.trans(BLOCK_SIZE * 9.0, BLOCK_SIZE * 11.0);
-    text::Text::new_color(color::WHITE, 30)
-        .draw(
-            "Click anywhere to restart",
-            glyphs,
-            &con.draw_state,
-            transform,
-            g,
-        )
-        .unwrap();
-}
-
-#[derive(Copy, Clone)]
-struct Animation {
-    time_remaining: f32,
-    position: Position,
-}
-
-fn main() {
-    let (width, height) = (30, 20);
-    let mut last_time = SystemTime::now();
-
-    let mut window: PistonWindow = WindowSettings::new(
-        "Battleship game",
-        [
-            ((width as f64) * BLOCK_SIZE) as u32,
-            ((height as f64) * BLOCK_SIZE) as u32,
-        ],
-    )
-    .exit_on_esc(true)
-    .build()
-    .unwrap();
-
-    let mut mouse = [0.0, 0.0];
-    let assets = find_folder::Search::ParentsThenKids(3, 3)
-        .for_folder("assets")
-        .unwrap();
-    println!("{:?}", assets);
-    let mut glyphs = window
-        .load_font(assets.join("FiraSans-Regular.ttf"))
-        .unwrap();
-    let mut game = GameState::initialize();
-    game.start();
-    // instantiate vecotr for storing animation of dropping
-    let mut own_board_animations: Vec<Animation> = Vec::new();
-    let mut enemy_board_animations: Vec<Animation> = Vec::new();
-
-    while let Some(event) = window.next() {
-        let current_time = SystemTime::now();
-        let duration_passed = current_time
-            .duration_since(last_time)
-            .expect("Time went backwards")
-            .as_millis();
-        last_time = current_time;
 
         // clear the window
         // custom draw method to rerender everything
@@ -543,85 +427,465 @@ fn main() {
         if let Some(pos) = event.mouse_cursor_args() {
             mouse = pos;
         }
-        if let Some(button) = event.press_args() {
-            // Check that coordinates are inside board boundaries.
-            if button == Button::Mouse(MouseButton::Left) {
-                if game.status == GameStatus::InProgress {
-                    // calculate if we are at a board location
-                    // check if it's on enemy board
-                    let x_grid = (mouse[0] / BLOCK_SIZE).floor() - ENEMY_OFFSET_X as f64;
-                    let y_grid = (mouse[1] / BLOCK_SIZE).floor() - ENEMY_OFFSET_Y as f64;
-                    if x_grid >= 0.0 && x_grid >= 0.0 && x_grid < 10.0 && y_grid < 10.0 {
-                        // check whether the placed was already attacked
-                        if game.attacks[0].get_cell_value(Position {
-                            x: x_grid as u8,
-                            y: y_grid as u8,
-                        }) == BoardCell::Empty
-                        {
-                            game.attack(
-                                PlayerID::P1,
-                                Position {
-                                    x: x_grid as u8,
-                                    y: y_grid as u8,
-                                },
-                                &mut enemy_board_animations,
-                            );
 
-                            // also have reaction for the attack
-                            game.randomly_attack(PlayerID::P2, &mut own_board_animations);
-                            // after attack check if game is over check p2 first then p1 since p1 attacks first
-                            game.check_winner();
+        // while a profile name is typed the keyboard belongs to the text field
+        if let Some(name) = &mut name_entry {
+            if let Some(text) = event.text_args() {
+                name.extend(
+                    text.chars()
+                        .filter(|c| !c.is_control())
+                        .take(24usize.saturating_sub(name.chars().count())),
+                );
+            }
+            match event.press_args() {
+                Some(Button::Keyboard(Key::Backspace)) => {
+                    name.pop();
+                }
+                Some(Button::Keyboard(Key::Return)) => {
+                    let name = name.trim().to_string();
+                    if !name.is_empty() && !name.starts_with("bot:") {
+                        profile_name = name;
+                        if let Some(store) = &mut store {
+                            store.select(&profile_name);
+                            if let Err(e) = store.save() {
+                                error_message = Some(e.to_string());
+                            }
+                        }
+                    }
+                    name_entry = None;
+                }
+                Some(Button::Keyboard(Key::Escape)) => name_entry = None,
+                _ => {}
+            }
+            window.draw_2d(&event, |c, g, device| {
+                clear(theme.background, g);
+                let buttons = menu_buttons(&settings, &profile_name);
+                let entry = name_entry.as_deref();
+                render_menu(&c, g, &mut glyphs, &theme, &buttons, entry, mouse);
+                glyphs.factory.encoder.flush(device);
+            });
+            continue;
+        }
+
+        if let Some(button) = event.press_args() {
+            let playing = matches!(screen, Screen::Playing);
+            let buttons = screen_buttons(&screen, &settings, &profile_name, focus);
+            let mut action = None;
+            // the enemy cell to shoot at, from a click or the keyboard cursor
+            let mut target = None;
+            match button {
+                Button::Keyboard(key) => {
+                    let keys = &settings.keys;
+                    if keys.quit.matches(key) {
+                        action = Some(Action::Quit);
+                    }
+                    // start over with the current rules
+                    if keys.restart.matches(key) {
+                        let opponent = bot_name(difficulty.name());
+                        let names = (profile_name.as_str(), opponent.as_str());
+                        if let Err(e) = forfeit(&mut store, &game, &mut history, names) {
+                            error_message = Some(e.to_string());
+                        }
+                        (game, history, difficulty) = new_game(
+                            &settings,
+                            &mut animator,
+                            &learned_shots(&store, &profile_name),
+                            learned_layouts(&store, &profile_name),
+                        );
+                        screen = Screen::Playing;
+                    }
+                    // cycle through the built-in themes, reloading the font if it changed
+                    if keys.next_theme.matches(key) {
+                        theme_index = (theme_index + 1) % themes.len();
+                        let next = themes[theme_index].clone();
+                        if next.font == theme.font {
+                            theme = next;
                         } else {
-                            // show already attacked
-                            println!("already attacked");
+                            match load_font(&mut window, &assets, &next.font) {
+                                Ok(new_glyphs) => {
+                                    glyphs = new_glyphs;
+                                    theme = next;
+                                }
+                                Err(e) => error_message = Some(e),
+                            }
                         }
                     }
+                    if keys.menu.matches(key) {
+                        action = Some(Action::MainMenu);
+                    }
+                    // after a game its analysis is what gets saved
+                    if keys.save.matches(key)
+                        && matches!(screen, Screen::GameOver | Screen::Analysis)
+                    {
+                        action = Some(Action::ExportAnalysis);
+                    }
+                    // the boards of a finished game and its replay can be
+                    // looked at below the surface too
+                    if keys.next_layer.matches(key) && game.rules().depth {
+                        layer = !layer;
+                        hint = None;
+                    }
+                    let step = if keys.up.matches(key) {
+                        Some((0, -1))
+                    } else if keys.down.matches(key) {
+                        Some((0, 1))
+                    } else if keys.left.matches(key) {
+                        Some((-1, 0))
+                    } else if keys.right.matches(key) {
+                        Some((1, 0))
+                    } else {
+                        None
+                    };
+                    if playing {
+                        // the cursor appears where the mouse was, or in the corner
+                        if let Some((dx, dy)) = step {
+                            let grid = game.rules().grid;
+                            let from = cursor.or_else(|| {
+                                board_cell_at(mouse, ENEMY_OFFSET_X, ENEMY_OFFSET_Y, grid)
+                            });
+                            cursor = Some(match from {
+                                Some(pos) => Position {
+                                    x: (pos.x as i32 + dx).clamp(0, 9) as u8,
+                                    y: (pos.y as i32 + dy).clamp(0, 9) as u8,
+                                    ..pos
+                                },
+                                None => Position {
+                                    x: 0,
+                                    y: 0,
+                                    depth: Depth::Surface,
+                                },
+                            });
+                        }
+                        if keys.fire.matches(key) {
+                            target = cursor;
+                        }
+                        if keys.hint.matches(key) {
+                            hint = game.hint(PlayerID::P1, layer);
+                        }
+                        if keys.heatmap.matches(key) {
+                            show_heatmap = !show_heatmap;
+                        }
+                        if keys.next_weapon.matches(key) {
+                            weapon = game.next_weapon(PlayerID::P1, weapon);
+                        }
+                        // the next enemy still in the game takes the big board
+                        if keys.next_target.matches(key) {
+                            let enemies = game.enemies(PlayerID::P1);
+                            let aimed = game.aim(PlayerID::P1);
+                            let next = enemies
+                                .iter()
+                                .position(|&e| e == aimed)
+                                .map(|i| (i + 1) % enemies.len())
+                                .unwrap_or(0);
+                            if let Some(&enemy) = enemies.get(next) {
+                                game.set_aim(PlayerID::P1, enemy);
+                                hint = None;
+                            }
+                        }
+                        if keys.turn_weapon.matches(key) {
+                            aim = !aim;
+                        }
+                        if keys.undo.matches(key) || keys.redo.matches(key) {
+                            let undo = keys.undo.matches(key);
+                            if !history.takebacks() {
+                                notice =
+                                    Some(String::from("shots can't be taken back in ranked games"));
+                            } else if undo && history.undo(&mut game)
+                                || !undo && history.redo(&mut game)
+                            {
+                                animator.clear();
+                                hint = None;
+                                notice = None;
+                            }
+                        }
+                        if keys.export_heatmap.matches(key) || keys.save.matches(key) {
+                            action = Some(Action::ExportHeatmap);
+                        }
+                    } else if !buttons.is_empty() {
+                        // buttons are in reading order so every direction walks the list
+                        if let Some((dx, dy)) = step {
+                            let len = buttons.len() as i32;
+                            focus = (focus as i32 + dx + dy).rem_euclid(len) as usize;
+                        }
+                        if keys.fire.matches(key) {
+                            action = buttons.get(focus).map(|b| b.action);
+                        }
+                    }
+                }
+                Button::Mouse(MouseButton::Left) => {
+                    action = clicked_button(&buttons, mouse);
+                    if playing {
+                        // calculate if we are at a board location
+                        // check if it's on enemy board
+                        target = aimed_cell(weapon, mouse, game.rules().grid);
+                        cursor = None;
+                        // a small board brings that enemy onto the big one
+                        if let Some(enemy) = small_board_at(&game, mouse) {
+                            if game.set_aim(PlayerID::P1, enemy) {
+                                hint = None;
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+            let before = std::mem::discriminant(&screen);
+            match action {
+                Some(Action::Play) | Some(Action::Rematch) => {
+                    (game, history, difficulty) = new_game(
+                        &settings,
+                        &mut animator,
+                        &learned_shots(&store, &profile_name),
+                        learned_layouts(&store, &profile_name),
+                    );
+                    screen = Screen::Playing;
+                }
+                Some(Action::ChangeDifficulty) => settings.difficulty = settings.difficulty.next(),
+                Some(Action::ToggleRanked) => settings.ranked = !settings.ranked,
+                Some(Action::NextProfile) => {
+                    if let Some(store) = &mut store {
+                        let names = store.human_names();
+                        let next = names
+                            .iter()
+                            .position(|n| *n == profile_name)
+                            .map(|i| (i + 1) % names.len())
+                            .unwrap_or(0);
+                        if let Some(name) = names.get(next) {
+                            profile_name = name.clone();
+                            store.select(&profile_name);
+                        }
+                    }
+                }
+                Some(Action::NewProfile) => name_entry = Some(String::new()),
+                Some(Action::ShowStats) => screen = Screen::Stats,
+                Some(Action::ShowLeaderboard) => screen = Screen::Leaderboard,
+                Some(Action::ForgetLayouts) => {
+                    if let Some(store) = &mut store {
+                        store.forget_layouts(&profile_name);
+                        match store.save() {
+                            Ok(()) => {
+                                notice = Some(String::from("the adaptive AI forgot your fleets"))
+                            }
+                            Err(e) => error_message = Some(e.to_string()),
+                        }
+                    }
+                }
+                Some(Action::Quit) => {
+                    let opponent = bot_name(difficulty.name());
+                    let names = (profile_name.as_str(), opponent.as_str());
+                    if let Err(e) = forfeit(&mut store, &game, &mut history, names) {
+                        eprintln!("{}", e);
+                    }
+                    window.set_should_close(true);
+                }
+                Some(Action::ViewReplay) => {
+                    if let Some((start, orders)) = history.replay() {
+                        animator.clear();
+                        screen = Screen::Replay(Box::new(Replay::new(
+                            start,
+                            orders,
+                            settings.animation_ms as f64 / 1000.0 * 1.5,
+                        )));
+                    }
+                }
+                Some(Action::ShowAnalysis) => screen = Screen::Analysis,
+                Some(Action::ExportAnalysis) => {
+                    if let Some(analysis) = &analysis {
+                        let path = export_path("analysis", "analysis", "txt");
+                        match analysis.write(&game, &path) {
+                            Ok(()) => notice = Some(format!("saved {}", path.display())),
+                            Err(e) => {
+                                error_message =
+                                    Some(format!("could not save {}: {}", path.display(), e))
+                            }
+                        }
+                    }
+                }
+                Some(Action::ExportHeatmap) => {
+                    let path = export_path("heatmaps", "heatmap", "csv");
+                    match stats::write_heatmap_csv(&game, &path) {
+                        Ok(()) => notice = Some(format!("saved {}", path.display())),
+                        Err(e) => {
+                            error_message =
+                                Some(format!("could not save {}: {}", path.display(), e))
+                        }
+                    }
+                }
+                Some(Action::MainMenu) => {
+                    let opponent = bot_name(difficulty.name());
+                    let names = (profile_name.as_str(), opponent.as_str());
+                    if let Err(e) = forfeit(&mut store, &game, &mut history, names) {
+                        error_message = Some(e.to_string());
+                    }
+                    animator.clear();
+                    screen = Screen::Menu;
+                }
+                Some(Action::BackToResults) => {
+                    animator.clear();
+                    screen = Screen::GameOver;
+                }
+                None => {}
+            }
+            if std::mem::discriminant(&screen) != before {
+                focus = 0;
+                cursor = None;
+                hint = None;
+                notice = None;
+            }
+            // only shots aimed while the board was showing, not the click that opened it
+            if let (true, Some(pos)) = (playing && matches!(screen, Screen::Playing), target) {
+                // the cell is on the layer the boards show
+                let order = Order {
+                    weapon,
+                    target: pos.at(layer),
+                    orientation: aim,
+                };
+                // the shot and the computer's answer, as one step that can be
+                // undone, unless the cell was already attacked or the like
+                if let Err(e) = history.play(&mut game, order, difficulty) {
+                    notice = Some(e);
                 } else {
-                    // restart the game
-                    game = GameState::initialize();
-                    game.start();
+                    hint = None;
+                    notice = None;
+                    if let GameStatus::Complete(_) = game.status {
+                        screen = Screen::GameOver;
+                        analysis = Some(Analysis::for_player(&game, PlayerID::P1));
+                        if let Some(store) = &mut store {
+                            let opponent = bot_name(difficulty.name());
+                            // the fleet as it was laid out, for the adaptive AI
+                            let opening = history.start().unwrap_or(&game);
+                            store.record_layout(
+                                &profile_name,
+                                &opening.players[0].all_ship_positions(),
+                            );
+                            store.record_shots(&profile_name, &game, PlayerID::P1);
+                            let names = (profile_name.as_str(), opponent.as_str());
+                            if let Err(e) = record_game(store, &game, &history, names) {
+                                eprintln!("{}", e);
+                                error_message = Some(e.to_string());
+                            }
+                        }
+                    }
                 }
-                // check if the game has ended which means either side has no ship left
             }
         }
-        // // update the animation time
-        for animation in &mut own_board_animations {
-            animation.time_remaining -= duration_passed as f32;
+        // a weapon that was used up, or isn't part of a new game, falls back to the shell
+        if !game.available(PlayerID::P1, weapon) {
+            weapon = Weapon::Shell;
+        }
+        if !game.rules().depth {
+            layer = Depth::Surface;
         }
-        for animation in &mut enemy_board_animations {
-            animation.time_remaining -= duration_passed as f32;
+        animator.follow(&game, layer);
+        for game_event in game.drain_events() {
+            animator.handle(&game_event);
         }
-        // filter out ones that are below 0
-        own_board_animations.retain(|animation| animation.time_remaining > 0.0);
-        enemy_board_animations.retain(|animation| animation.time_remaining > 0.0);
 
+        let buttons = screen_buttons(&screen, &settings, &profile_name, focus);
         window.draw_2d(&event, |c, g, device| {
-            clear(BACK_COLOR, g);
-            // check the current game state and render accordingly
-            match game.status {
-                GameStatus::InProgress => {
-                    render(&c, g, &mut glyphs, &mut game);
-                    render_animations(&c, g, &mut own_board_animations, OWN_OFFSET_X, OWN_OFFSET_Y);
-                    render_animations(
+            clear(theme.background, g);
+            // check the current screen and render accordingly
+            match (&screen, game.status) {
+                (Screen::Menu, _) => {
+                    render_menu(&c, g, &mut glyphs, &theme, &buttons, None, mouse);
+                }
+                (Screen::GameOver, GameStatus::Complete(_)) => {
+                    let shown_layer = (layer, &settings.keys.next_layer);
+                    let finished = (&game, analysis.as_ref(), shown_layer);
+                    render_game_over(&c, g, &mut glyphs, &theme, finished, &buttons, mouse);
+                }
+                (Screen::Analysis, _) => {
+                    if let Some(analysis) = &analysis {
+                        render_analysis(&c, g, &mut glyphs, &theme, analysis, &buttons, mouse);
+                    }
+                }
+                (Screen::Replay(replay), _) => {
+                    let shown = (replay.as_ref(), (layer, &settings.keys.next_layer));
+                    render_replay(&c, g, &mut glyphs, &theme, shown, &buttons, mouse);
+                }
+                (Screen::Stats, _) => {
+                    let profile = store.as_ref().and_then(|s| s.get(&profile_name));
+                    render_stats(
                         &c,
                         g,
-                        &mut enemy_board_animations,
-                        ENEMY_OFFSET_X,
-                        ENEMY_OFFSET_Y,
+                        &mut glyphs,
+                        &theme,
+                        (&profile_name, profile),
+                        &buttons,
+                        mouse,
                     );
                 }
-                GameStatus::Complete(winner) => {
-                    render_winning_screen(&c, g, &mut glyphs, winner);
+                (Screen::Leaderboard, _) => {
+                    let entries = match &store {
+                        Some(store) => store.leaderboard(),
+                        None => vec![],
+                    };
+                    render_leaderboard(
+                        &c,
+                        g,
+                        &mut glyphs,
+                        &theme,
+                        (&entries, &profile_name),
+                        &buttons,
+                        mouse,
+                    );
                 }
-                GameStatus::NotStarted => {
-                    // should be ignored but render board anyways
-                    render(&c, g, &mut glyphs, &mut game);
+                _ => {
+                    render(
+                        &c,
+                        g,
+                        &mut glyphs,
+                        &theme,
+                        &mut game,
+                        mouse,
+                        (cursor, (weapon, aim, layer), &settings.keys),
+                    );
+                    if show_heatmap {
+                        let aimed = game.aim(PlayerID::P1);
+                        let enemy = game.heatmap(PlayerID::P1, aimed, layer);
+                        let (x, y) = (ENEMY_OFFSET_X, ENEMY_OFFSET_Y);
+                        let seen = game.seen(PlayerID::P1, aimed).layer(layer);
+                        render_heatmap(&c, g, &theme, &enemy, &seen, x, y);
+                        // ours as the aimed enemy sees it
+                        let own = game.heatmap(aimed, PlayerID::P1, layer);
+                        let (x, y) = (OWN_OFFSET_X, OWN_OFFSET_Y);
+                        let seen = game.seen(aimed, PlayerID::P1).layer(layer);
+                        render_heatmap(&c, g, &theme, &own, &seen, x, y);
+                    }
+                    if let Some(hint) = &hint {
+                        render_hint(&c, g, &mut glyphs, &theme, hint, game.rules().grid);
+                    }
+                    let rules = game.rules();
+                    if rules.special_weapons
+                        || rules.ship_abilities
+                        || rules.mobile_fleet
+                        || rules.repairs
+                    {
+                        let target = cursor
+                            .or_else(|| aimed_cell(weapon, mouse, rules.grid))
+                            .map(|p| p.at(layer));
+                        let selected = (weapon, aim, layer);
+                        let target = (target, &settings.keys);
+                        render_arsenal(&c, g, &mut glyphs, &theme, &game, selected, target);
+                    }
                 }
             }
+            animator.render(&c, g, &mut glyphs, &theme);
+            if let Some(error) = &error_message {
+                render_error(&c, g, &mut glyphs, &theme, error);
+            } else if let Some(notice) = &notice {
+                render_notice(&c, g, &mut glyphs, &theme, notice);
+            }
             glyphs.factory.encoder.flush(device);
         });
         // print time
     }
+    // closing the window in the middle of a ranked game loses it too
+    let opponent = bot_name(difficulty.name());
+    let names = (profile_name.as_str(), opponent.as_str());
+    if let Err(e) = forfeit(&mut store, &game, &mut history, names) {
+        eprintln!("{}", e);
+    }
     // let mut siv = cursive::default();
     // siv.add_global_callback('q', |s| s.quit());
 
@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::ai::{add_shots, LayoutPrior, ShotHistory};
+use crate::game::{GameState, PlayerID, Position};
+use crate::rating::{rate_game, Rating};
+use crate::stats::MatchStats;
+
+#[derive(Debug)]
+pub enum ProfileError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Write(toml::ser::Error),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileError::Io(path, e) => write!(f, "could not access {}: {}", path.display(), e),
+            ProfileError::Parse(path, e) => write!(f, "error in {}: {}", path.display(), e),
+            ProfileError::Write(e) => write!(f, "could not save profiles: {}", e),
+        }
+    }
+}
+
+// where profiles and other data is kept, $BATTLESHIP_HOME or ~/.battleship
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = env::var_os("BATTLESHIP_HOME") {
+        return PathBuf::from(dir);
+    }
+    match env::var_os("HOME").or_else(|| env::var_os("USERPROFILE")) {
+        Some(home) => PathBuf::from(home).join(".battleship"),
+        None => PathBuf::from(".battleship"),
+    }
+}
+
+// the outcome of one game from one side, what gets added to a profile
+#[derive(Debug, Clone)]
+pub struct GameResult {
+    pub opponent: String,
+    pub variant: String,
+    pub won: bool,
+    pub shots: u32,
+    pub hits: u32,
+}
+
+impl GameResult {
+    pub fn from_game(game: &GameState, player: PlayerID, opponent: &str) -> Self {
+        let stats = MatchStats::for_player(&game.shots, player);
+        GameResult {
+            opponent: String::from(opponent),
+            variant: game.rules().variant(),
+            won: game.won(player),
+            shots: stats.shots,
+            hits: stats.hits,
+        }
+    }
+}
+
+// totals against one opponent type with one rule variant
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Record {
+    pub played: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub shots: u32,
+    pub hits: u32,
+    // shots fired over all won games, for the average
+    pub winning_shots: u32,
+    // fewest shots needed for a win
+    pub best_game: Option<u32>,
+}
+
+impl Record {
+    fn add(&mut self, result: &GameResult) {
+        self.played += 1;
+        self.shots += result.shots;
+        self.hits += result.hits;
+        if result.won {
+            self.wins += 1;
+            self.winning_shots += result.shots;
+            self.best_game = Some(match self.best_game {
+                Some(best) => best.min(result.shots),
+                None => result.shots,
+            });
+        } else {
+            self.losses += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &Record) {
+        self.played += other.played;
+        self.wins += other.wins;
+        self.losses += other.losses;
+        self.shots += other.shots;
+        self.hits += other.hits;
+        self.winning_shots += other.winning_shots;
+        self.best_game = match (self.best_game, other.best_game) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+    }
+
+    pub fn accuracy(&self) -> f64 {
+        if self.shots == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.shots as f64
+        }
+    }
+
+    pub fn average_shots_to_win(&self) -> Option<f64> {
+        if self.wins == 0 {
+            None
+        } else {
+            Some(self.winning_shots as f64 / self.wins as f64)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    // bots are keyed by their strategy name and not offered in the menu
+    pub bot: bool,
+    // "<opponent> / <variant>" -> totals
+    pub records: BTreeMap<String, Record>,
+    pub rating: Rating,
+    // the fleets laid out in past games, for the adaptive AI
+    pub layout: LayoutPrior,
+    // the cells they fired at in past games, for adaptive placement
+    pub fired_at: ShotHistory,
+}
+
+impl Profile {
+    pub fn record(&mut self, result: &GameResult) {
+        let key = format!("{} / {}", result.opponent, result.variant);
+        self.records.entry(key).or_default().add(result);
+    }
+
+    pub fn career(&self) -> Record {
+        let mut total = Record::default();
+        for record in self.records.values() {
+            total.merge(record);
+        }
+        total
+    }
+}
+
+pub fn bot_name(strategy: &str) -> String {
+    format!("bot:{}", strategy)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct StoreFile {
+    last_profile: Option<String>,
+    profiles: BTreeMap<String, Profile>,
+}
+
+// every profile on this machine, kept in a single TOML file
+pub struct ProfileStore {
+    path: PathBuf,
+    file: StoreFile,
+}
+
+impl ProfileStore {
+    pub fn default_path() -> PathBuf {
+        data_dir().join("profiles.toml")
+    }
+
+    // read the store, an absent file is an empty store
+    pub fn open(path: &Path) -> Result<Self, ProfileError> {
+        let file = if path.exists() {
+            let text =
+                fs::read_to_string(path).map_err(|e| ProfileError::Io(path.to_path_buf(), e))?;
+            toml::from_str(&text).map_err(|e| ProfileError::Parse(path.to_path_buf(), e))?
+        } else {
+            StoreFile::default()
+        };
+        Ok(ProfileStore {
+            path: path.to_path_buf(),
+            file,
+        })
+    }
+
+    pub fn save(&self) -> Result<(), ProfileError> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| ProfileError::Io(dir.to_path_buf(), e))?;
+        }
+        let text = toml::to_string(&self.file).map_err(ProfileError::Write)?;
+        fs::write(&self.path, text).map_err(|e| ProfileError::Io(self.path.clone(), e))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.file.profiles.get(name)
+    }
+
+    pub fn human_names(&self) -> Vec<String> {
+        self.file
+            .profiles
+            .iter()
+            .filter(|(_, p)| !p.bot)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    pub fn last_profile(&self) -> Option<&str> {
+        self.file.last_profile.as_deref()
+    }
+
+    // create the profile if needed and remember it as the one in use
+    pub fn select(&mut self, name: &str) {
+        self.file.profiles.entry(String::from(name)).or_default();
+        self.file.last_profile = Some(String::from(name));
+    }
+
+    pub fn record(&mut self, name: &str, bot: bool, result: &GameResult) {
+        let profile = self.file.profiles.entry(String::from(name)).or_default();
+        profile.bot = bot;
+        profile.record(result);
+    }
+
+    pub fn record_layout(&mut self, name: &str, fleet: &[Position]) {
+        let profile = self.file.profiles.entry(String::from(name)).or_default();
+        profile.layout.record(fleet);
+    }
+
+    pub fn record_shots(&mut self, name: &str, game: &GameState, player: PlayerID) {
+        let profile = self.file.profiles.entry(String::from(name)).or_default();
+        add_shots(&mut profile.fired_at, &game.shots, player);
+    }
+
+    pub fn forget_layouts(&mut self, name: &str) {
+        if let Some(profile) = self.file.profiles.get_mut(name) {
+            profile.layout = LayoutPrior::default();
+        }
+    }
+
+    // move both ratings after a game between two profiles, creating them if needed
+    pub fn rate(&mut self, winner: &str, loser: &str) {
+        if winner == loser {
+            return;
+        }
+        let mut winning = self.file.profiles.remove(winner).unwrap_or_default();
+        let losing = self.file.profiles.entry(String::from(loser)).or_default();
+        rate_game(&mut winning.rating, &mut losing.rating);
+        self.file.profiles.insert(String::from(winner), winning);
+    }
+
+    // humans and bots by rating, highest first
+    pub fn leaderboard(&self) -> Vec<(&str, &Profile)> {
+        let mut entries: Vec<(&str, &Profile)> = self
+            .file
+            .profiles
+            .iter()
+            .filter(|(_, p)| p.rating.games > 0)
+            .map(|(name, p)| (name.as_str(), p))
+            .collect();
+        entries.sort_by(|a, b| b.1.rating.value.total_cmp(&a.1.rating.value));
+        entries
+    }
+}
@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+
+// everyone starts here, bots included
+pub const INITIAL_RATING: f64 = 1200.0;
+// games before a rating is trusted, it moves faster until then
+pub const PROVISIONAL_GAMES: u32 = 10;
+const PROVISIONAL_K: f64 = 40.0;
+const ESTABLISHED_K: f64 = 20.0;
+
+// an Elo style rating with the value after every rated game
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Rating {
+    pub value: f64,
+    pub games: u32,
+    pub history: Vec<f64>,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            value: INITIAL_RATING,
+            games: 0,
+            history: vec![],
+        }
+    }
+}
+
+impl Rating {
+    pub fn provisional(&self) -> bool {
+        self.games < PROVISIONAL_GAMES
+    }
+
+    // how much a single game can move this rating, an established player
+    // facing a newcomer risks less since the newcomer's rating is a guess
+    fn k_factor(&self, opponent: &Rating) -> f64 {
+        if self.provisional() {
+            PROVISIONAL_K
+        } else if opponent.provisional() {
+            ESTABLISHED_K / 2.0
+        } else {
+            ESTABLISHED_K
+        }
+    }
+
+    fn adjust(&mut self, change: f64) {
+        self.value += change;
+        self.games += 1;
+        self.history.push(self.value);
+    }
+
+    // "1234" or "1234?" while still provisional
+    pub fn label(&self) -> String {
+        if self.provisional() {
+            format!("{:.0}?", self.value)
+        } else {
+            format!("{:.0}", self.value)
+        }
+    }
+}
+
+// the chance that a player rated `a` beats one rated `b`
+pub fn expected_score(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+// update both ratings after a game the winner won
+pub fn rate_game(winner: &mut Rating, loser: &mut Rating) {
+    let expected = expected_score(winner.value, loser.value);
+    let winner_k = winner.k_factor(loser);
+    let loser_k = loser.k_factor(winner);
+    winner.adjust(winner_k * (1.0 - expected));
+    loser.adjust(-loser_k * (1.0 - expected));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn after_games(games: u32) -> Rating {
+        Rating {
+            games,
+            ..Rating::default()
+        }
+    }
+
+    #[test]
+    fn even_ratings_expect_an_even_game() {
+        assert_eq!(expected_score(1500.0, 1500.0), 0.5);
+        assert!(expected_score(1600.0, 1400.0) > 0.75);
+        let sum = expected_score(1300.0, 1700.0) + expected_score(1700.0, 1300.0);
+        assert!((sum - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn newcomers_move_by_the_provisional_k() {
+        let (mut winner, mut loser) = (Rating::default(), Rating::default());
+        rate_game(&mut winner, &mut loser);
+        assert_eq!(winner.value, INITIAL_RATING + PROVISIONAL_K / 2.0);
+        assert_eq!(loser.value, INITIAL_RATING - PROVISIONAL_K / 2.0);
+        assert_eq!((winner.games, loser.games), (1, 1));
+        assert_eq!(winner.history, vec![winner.value]);
+    }
+
+    #[test]
+    fn established_players_move_by_the_established_k() {
+        let (mut winner, mut loser) = (after_games(PROVISIONAL_GAMES), after_games(50));
+        rate_game(&mut winner, &mut loser);
+        assert_eq!(winner.value, INITIAL_RATING + ESTABLISHED_K / 2.0);
+        assert_eq!(loser.value, INITIAL_RATING - ESTABLISHED_K / 2.0);
+    }
+
+    #[test]
+    fn established_players_risk_less_against_newcomers() {
+        let (mut newcomer, mut veteran) = (Rating::default(), after_games(30));
+        rate_game(&mut newcomer, &mut veteran);
+        assert_eq!(newcomer.value, INITIAL_RATING + PROVISIONAL_K / 2.0);
+        assert_eq!(veteran.value, INITIAL_RATING - ESTABLISHED_K / 4.0);
+    }
+
+    #[test]
+    fn ratings_are_provisional_for_the_first_games() {
+        let mut rating = Rating::default();
+        for _ in 0..PROVISIONAL_GAMES - 1 {
+            rating.adjust(0.0);
+        }
+        assert!(rating.provisional());
+        assert_eq!(rating.label(), "1200?");
+        rating.adjust(0.0);
+        assert!(!rating.provisional());
+        assert_eq!(rating.label(), "1200");
+    }
+}
@@ -0,0 +1,1364 @@
+use piston_window::*;
+
+use crate::ai::Hint;
+use crate::analysis::{Analysis, Grade};
+use crate::game::{
+    Board, BoardCell, Depth, GameState, GameStatus, Grid, PlayerID, Position, Ship,
+    ShipOrientation, ShipStatus,
+};
+use crate::profile::{Profile, Record};
+use crate::rating::{Rating, PROVISIONAL_GAMES};
+use crate::replay::Replay;
+use crate::settings::{Binding, KeyBindings};
+use crate::stats::{format_duration, MatchStats};
+use crate::theme::Theme;
+use crate::utils::{
+    draw_block, draw_cell_outline, draw_cell_tint, draw_cross, draw_dot, draw_hex, draw_ring,
+    draw_ship_outline, row_context,
+};
+use crate::weapons::{Order, Weapon};
+
+pub const OWN_OFFSET_X: i32 = 3;
+pub const OWN_OFFSET_Y: i32 = 3;
+pub const ENEMY_OFFSET_X: i32 = 18;
+pub const ENEMY_OFFSET_Y: i32 = 3;
+pub const PANEL_OFFSET_X: i32 = 30;
+pub const PANEL_OFFSET_Y: i32 = 3;
+pub const BLOCK_SIZE: f64 = 25.0;
+// the boards of the other opponents are drawn at this size between ours
+const SMALL_BOARD_SCALE: f64 = 0.2;
+const COLUMN_LABELS: [&str; 10] = ["A", "B", "C", "D", "E", "F", "G", "H", "I", "J"];
+
+impl ShipStatus {
+    pub fn color(&self, theme: &Theme) -> [f32; 4] {
+        match self {
+            ShipStatus::Undamaged => theme.afloat,
+            ShipStatus::Damaged => theme.damaged,
+            ShipStatus::Sunk => theme.sunk,
+        }
+    }
+}
+
+impl Board {
+    pub fn render_board(
+        &self,
+        con: &Context,
+        g: &mut G2d,
+        theme: &Theme,
+        x_offset: i32,
+        y_offset: i32,
+    ) {
+        for row in 0i32..10 {
+            for col in 0i32..10 {
+                let (x, y) = (row + x_offset, col + y_offset);
+                let pos = Position {
+                    x: row as u8,
+                    y: col as u8,
+                    depth: Depth::Surface,
+                };
+                let con = &row_context(con, self.grid(), pos.y);
+                let color = match self.get_cell_value(pos) {
+                    BoardCell::Empty => theme.water,
+                    BoardCell::Ship => theme.ship,
+                    BoardCell::DamagedShip => theme.damaged_ship,
+                    BoardCell::FailedAttack => theme.miss,
+                    BoardCell::SuccessfulAttack => theme.hit,
+                    BoardCell::Island => theme.island,
+                    BoardCell::Mine => theme.mine,
+                    BoardCell::Repaired => theme.repaired,
+                    BoardCell::Pending => theme.pending,
+                };
+                match self.grid() {
+                    Grid::Square => draw_block(color, theme.grid_line, x, y, con, g),
+                    Grid::Hex => draw_hex(color, theme.grid_line, x, y, con, g),
+                }
+                // shape overlays so hits and misses differ without color
+                if theme.overlays {
+                    match self.get_cell_value(pos) {
+                        BoardCell::DamagedShip | BoardCell::SuccessfulAttack => {
+                            draw_cross(theme.hit_overlay, x, y, con, g)
+                        }
+                        BoardCell::FailedAttack => draw_dot(theme.miss_overlay, x, y, con, g),
+                        BoardCell::Mine => draw_dot(theme.hit_overlay, x, y, con, g),
+                        BoardCell::Repaired => draw_ring(theme.hit_overlay, x, y, con, g),
+                        BoardCell::Pending => draw_dot(theme.hit_overlay, x, y, con, g),
+                        BoardCell::Empty | BoardCell::Ship | BoardCell::Island => {}
+                    }
+                }
+            }
+        }
+    }
+}
+
+// convert a mouse position into a cell of the board drawn at the given offset
+pub fn board_cell_at(
+    mouse: [f64; 2],
+    offset_x: i32,
+    offset_y: i32,
+    grid: Grid,
+) -> Option<Position> {
+    if grid == Grid::Hex {
+        return hex_cell_at(mouse, offset_x, offset_y);
+    }
+    let x_grid = (mouse[0] / BLOCK_SIZE).floor() - offset_x as f64;
+    let y_grid = (mouse[1] / BLOCK_SIZE).floor() - offset_y as f64;
+    if (0.0..10.0).contains(&x_grid) && (0.0..10.0).contains(&y_grid) {
+        Some(Position {
+            x: x_grid as u8,
+            y: y_grid as u8,
+            depth: Depth::Surface,
+        })
+    } else {
+        None
+    }
+}
+
+// the hexagons are regular ones stretched so the rows are a block apart, once
+// the stretch is undone the cell under the mouse is the one with the nearest
+// middle, which may be off the board
+fn hex_cell_at(mouse: [f64; 2], offset_x: i32, offset_y: i32) -> Option<Position> {
+    let x = mouse[0] / BLOCK_SIZE - offset_x as f64;
+    let y = mouse[1] / BLOCK_SIZE - offset_y as f64;
+    let squash = 3f64.sqrt() / 2.0;
+    let row = y.floor() as i32;
+    let mut nearest = (f64::MAX, 0, 0);
+    for r in row - 1..=row + 1 {
+        let shift = if r.rem_euclid(2) == 1 { 0.5 } else { 0.0 };
+        let col = (x - shift).floor() as i32;
+        for c in col - 1..=col + 1 {
+            let dx = x - (c as f64 + shift + 0.5);
+            let dy = (y - (r as f64 + 0.5)) * squash;
+            let distance = dx * dx + dy * dy;
+            if distance < nearest.0 {
+                nearest = (distance, c, r);
+            }
+        }
+    }
+    let (_, c, r) = nearest;
+    ((0..10).contains(&c) && (0..10).contains(&r)).then_some(Position {
+        x: c as u8,
+        y: r as u8,
+        depth: Depth::Surface,
+    })
+}
+
+// draw A-J above and 1-10 left of the board at the given offset
+fn render_labels(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    offset_x: i32,
+    offset_y: i32,
+) {
+    for (i, label) in COLUMN_LABELS.iter().enumerate() {
+        let transform = con.transform.trans(
+            BLOCK_SIZE * (offset_x as f64 + i as f64 + 0.3),
+            BLOCK_SIZE * (offset_y as f64 - 0.25),
+        );
+        text::Text::new_color(theme.label, theme.label_size)
+            .draw(label, glyphs, &con.draw_state, transform, g)
+            .unwrap();
+    }
+    for i in 0..10 {
+        let transform = con.transform.trans(
+            BLOCK_SIZE * (offset_x as f64 - 0.8),
+            BLOCK_SIZE * (offset_y as f64 + i as f64 + 0.7),
+        );
+        text::Text::new_color(theme.label, theme.label_size)
+            .draw(&(i + 1).to_string(), glyphs, &con.draw_state, transform, g)
+            .unwrap();
+    }
+}
+
+// list every ship of a fleet with its length and state, returns the next free row
+fn render_fleet_status(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    title: &str,
+    fleet: &[(&Ship, ShipStatus)],
+    row: i32,
+) -> i32 {
+    let transform = con
+        .transform
+        .trans(BLOCK_SIZE * PANEL_OFFSET_X as f64, BLOCK_SIZE * row as f64);
+    text::Text::new_color(theme.text, theme.heading_size)
+        .draw(title, glyphs, &con.draw_state, transform, g)
+        .unwrap();
+    let mut row = row + 1;
+    for &(ship, status) in fleet {
+        let transform = con
+            .transform
+            .trans(BLOCK_SIZE * PANEL_OFFSET_X as f64, BLOCK_SIZE * row as f64);
+        text::Text::new_color(status.color(theme), theme.label_size)
+            .draw(
+                &format!("{} ({})", ship.kind.name(), ship.size),
+                glyphs,
+                &con.draw_state,
+                transform,
+                g,
+            )
+            .unwrap();
+        let transform = con.transform.trans(
+            BLOCK_SIZE * (PANEL_OFFSET_X as f64 + 5.5),
+            BLOCK_SIZE * row as f64,
+        );
+        text::Text::new_color(status.color(theme), theme.label_size)
+            .draw(status.label(), glyphs, &con.draw_state, transform, g)
+            .unwrap();
+        row += 1;
+    }
+    row
+}
+
+// a clickable rectangle with a label, positioned in pixels, that stands for
+// some action of the caller's choosing
+pub struct MenuButton<A> {
+    pub action: A,
+    pub label: String,
+    pub rect: [f64; 4],
+    // selected with the keyboard, drawn like a hovered button
+    pub focused: bool,
+}
+
+impl<A: Copy> MenuButton<A> {
+    // a button in block units, like the rest of the layout
+    pub fn new(action: A, label: &str, x: f64, y: f64, width: f64) -> Self {
+        MenuButton {
+            action,
+            label: String::from(label),
+            rect: [
+                x * BLOCK_SIZE,
+                y * BLOCK_SIZE,
+                width * BLOCK_SIZE,
+                BLOCK_SIZE * 1.2,
+            ],
+            focused: false,
+        }
+    }
+
+    pub fn contains(&self, mouse: [f64; 2]) -> bool {
+        let [x, y, w, h] = self.rect;
+        mouse[0] >= x && mouse[0] < x + w && mouse[1] >= y && mouse[1] < y + h
+    }
+
+    pub fn render(
+        &self,
+        con: &Context,
+        g: &mut G2d,
+        glyphs: &mut Glyphs,
+        theme: &Theme,
+        mouse: [f64; 2],
+    ) {
+        let fill = if self.focused || self.contains(mouse) {
+            theme.button_hover
+        } else {
+            theme.button
+        };
+        rectangle(fill, self.rect, con.transform, g);
+        let transform = con
+            .transform
+            .trans(self.rect[0] + 8.0, self.rect[1] + self.rect[3] * 0.72);
+        text::Text::new_color(theme.text, theme.text_size)
+            .draw(&self.label, glyphs, &con.draw_state, transform, g)
+            .unwrap();
+    }
+}
+
+// the action of the button under the mouse, if any
+pub fn clicked_button<A: Copy>(buttons: &[MenuButton<A>], mouse: [f64; 2]) -> Option<A> {
+    buttons.iter().find(|b| b.contains(mouse)).map(|b| b.action)
+}
+
+// "Enemy" with a single opponent, "Player 3" with several
+fn who(game: &GameState, player: PlayerID) -> String {
+    if game.players.len() == 2 {
+        String::from("Enemy")
+    } else {
+        player.name()
+    }
+}
+
+// "Enemy" with a single opponent, "Player 3's" with several
+fn whose(game: &GameState, player: PlayerID) -> String {
+    if game.players.len() == 2 {
+        String::from("Enemy")
+    } else {
+        format!("{}'s", player.name())
+    }
+}
+
+// the top left corner of the small board of every opponent, stacked between
+// our board and the aimed one; only games with more than two fleets have them
+fn small_boards(game: &GameState) -> Vec<(PlayerID, [f64; 2])> {
+    if game.players.len() == 2 {
+        return vec![];
+    }
+    (0..game.players.len())
+        .map(PlayerID)
+        .filter(|&p| !game.allies(PlayerID::P1, p))
+        .enumerate()
+        .map(|(i, p)| (p, [BLOCK_SIZE * 14.5, BLOCK_SIZE * (2.6 + 2.55 * i as f64)]))
+        .collect()
+}
+
+// the opponent whose small board or its label is under the mouse
+pub fn small_board_at(game: &GameState, mouse: [f64; 2]) -> Option<PlayerID> {
+    let size = BLOCK_SIZE * 10.0 * SMALL_BOARD_SCALE;
+    small_boards(game)
+        .into_iter()
+        .find(|(_, [x, y])| {
+            (*x..x + size).contains(&mouse[0])
+                && (*y..y + size + BLOCK_SIZE * 0.55).contains(&mouse[1])
+        })
+        .map(|(player, _)| player)
+}
+
+// every opponent's board as we see it on the shown layer, the aimed one
+// outlined and the ones whose fleet is gone marked as out
+fn render_small_boards(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    game: &GameState,
+    layer: Depth,
+) {
+    let size = BLOCK_SIZE * 10.0 * SMALL_BOARD_SCALE;
+    for (player, [x, y]) in small_boards(game) {
+        let aimed = game.aim(PlayerID::P1) == player;
+        let label = if game.is_out(player) {
+            format!("P{} out", player.index() + 1)
+        } else {
+            format!("P{}", player.index() + 1)
+        };
+        let color = if aimed { theme.title } else { theme.label };
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(color, theme.label_size),
+            &label,
+            x / BLOCK_SIZE,
+            y / BLOCK_SIZE + 0.45,
+        );
+        let top = y + BLOCK_SIZE * 0.55;
+        let small = con
+            .trans(x, top)
+            .scale(SMALL_BOARD_SCALE, SMALL_BOARD_SCALE);
+        game.seen(PlayerID::P1, player)
+            .layer(layer)
+            .render_board(&small, g, theme, 0, 0);
+        if aimed {
+            Rectangle::new_border(theme.hover_valid, 1.5).draw(
+                [x - 1.5, top - 1.5, size + 3.0, size + 3.0],
+                &con.draw_state,
+                con.transform,
+                g,
+            );
+        }
+    }
+}
+
+// draw one layer of our board and the aimed enemy's side by side with their
+// labels, sunk ships and last shots
+fn render_boards(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    game: &GameState,
+    (own, enemy): (&Board, &Board),
+    (layer, switch): (Depth, &Binding),
+) {
+    // draw the grid
+
+    own.layer(layer)
+        .render_board(con, g, theme, OWN_OFFSET_X, OWN_OFFSET_Y);
+    enemy
+        .layer(layer)
+        .render_board(con, g, theme, ENEMY_OFFSET_X, ENEMY_OFFSET_Y);
+    render_labels(con, g, glyphs, theme, OWN_OFFSET_X, OWN_OFFSET_Y);
+    render_labels(con, g, glyphs, theme, ENEMY_OFFSET_X, ENEMY_OFFSET_Y);
+    if game.rules().depth {
+        // both boards show the same layer
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.label, theme.label_size),
+            &format!(
+                "{} layer on both boards, {} switches",
+                layer.name(),
+                switch.label()
+            ),
+            OWN_OFFSET_X as f64,
+            13.8,
+        );
+    }
+
+    // outline the sunk ships on both boards
+    let aimed = game.aim(PlayerID::P1);
+    let grid = game.rules().grid;
+    let shown = |s: &&Ship| s.positions().iter().all(|p| p.depth == layer);
+    for ship in game.players[0]
+        .ships()
+        .iter()
+        .filter(|s| s.is_sunk())
+        .filter(shown)
+    {
+        draw_ship_outline(
+            theme.sunk_outline,
+            &ship.positions(),
+            grid,
+            (OWN_OFFSET_X, OWN_OFFSET_Y),
+            con,
+            g,
+        );
+    }
+    let reported_sunk =
+        |s: &&Ship| game.reported_status(PlayerID::P1, aimed, s) == ShipStatus::Sunk;
+    for ship in game.players[aimed.index()]
+        .ships()
+        .iter()
+        .filter(reported_sunk)
+        .filter(shown)
+    {
+        draw_ship_outline(
+            theme.sunk_outline,
+            &ship.positions(),
+            grid,
+            (ENEMY_OFFSET_X, ENEMY_OFFSET_Y),
+            con,
+            g,
+        );
+    }
+
+    // mark the last shot of each side, the enemies' land on our board
+    for last in &game.last_attacks[1..] {
+        if let Some((PlayerID::P1, pos)) = last.filter(|(_, p)| p.depth == layer) {
+            draw_ring(
+                theme.last_shot,
+                pos.x as i32 + OWN_OFFSET_X,
+                pos.y as i32 + OWN_OFFSET_Y,
+                &row_context(con, grid, pos.y),
+                g,
+            );
+        }
+    }
+    if let Some((_, pos)) = game.last_attacks[0].filter(|&(t, p)| t == aimed && p.depth == layer) {
+        draw_ring(
+            theme.last_shot,
+            pos.x as i32 + ENEMY_OFFSET_X,
+            pos.y as i32 + ENEMY_OFFSET_Y,
+            &row_context(con, grid, pos.y),
+            g,
+        );
+    }
+}
+
+fn render_text(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    style: text::Text,
+    message: &str,
+    x: f64,
+    y: f64,
+) {
+    let transform = con.transform.trans(BLOCK_SIZE * x, BLOCK_SIZE * y);
+    style
+        .draw(message, glyphs, &con.draw_state, transform, g)
+        .unwrap();
+}
+
+// helper method to render a game state, showing one layer of water
+pub fn render(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    game: &mut GameState,
+    mouse: [f64; 2],
+    (cursor, (weapon, aim, layer), keys): (
+        Option<Position>,
+        (Weapon, ShipOrientation, Depth),
+        &KeyBindings,
+    ),
+) {
+    let aimed = game.aim(PlayerID::P1);
+    let seen = game.seen(PlayerID::P1, aimed);
+    render_boards(
+        con,
+        g,
+        glyphs,
+        theme,
+        game,
+        (&game.ships[0], &seen),
+        (layer, &keys.next_layer),
+    );
+    render_small_boards(con, g, glyphs, theme, game, layer);
+
+    // highlight the hovered cell, dimmed if the order couldn't be given there;
+    // moving or repairing a ship points at our own board
+    if game.status == GameStatus::InProgress {
+        let grid = seen.grid();
+        let (x, y) = if weapon.own_board() {
+            (OWN_OFFSET_X, OWN_OFFSET_Y)
+        } else {
+            (ENEMY_OFFSET_X, ENEMY_OFFSET_Y)
+        };
+        // the keyboard cursor takes over from the mouse until the next click
+        if let Some(pos) = cursor.or_else(|| board_cell_at(mouse, x, y, grid)) {
+            let order = Order {
+                weapon,
+                target: pos.at(layer),
+                orientation: aim,
+            };
+            let color = if game.can_fire(PlayerID::P1, &order) {
+                theme.hover_valid
+            } else {
+                theme.hover_invalid
+            };
+            draw_cell_outline(
+                color,
+                3.0,
+                grid,
+                pos.x as i32 + x,
+                pos.y as i32 + y,
+                &row_context(con, grid, pos.y),
+                g,
+            );
+        }
+    }
+    // where sonar found a ship or a mine gave one away
+    for contact in game
+        .contacts(PlayerID::P1, aimed)
+        .into_iter()
+        .filter(|c| c.centre.depth == layer)
+    {
+        draw_ship_outline(
+            theme.hint,
+            &contact.area,
+            seen.grid(),
+            (ENEMY_OFFSET_X, ENEMY_OFFSET_Y),
+            con,
+            g,
+        );
+    }
+    // fleet status panel to the right of the boards, the enemy's as far as
+    // we were told
+    let own: Vec<(&Ship, ShipStatus)> = game.players[0]
+        .ships()
+        .iter()
+        .map(|s| (s, s.status))
+        .collect();
+    let enemy: Vec<(&Ship, ShipStatus)> = game.players[aimed.index()]
+        .ships()
+        .iter()
+        .map(|s| (s, game.reported_status(PlayerID::P1, aimed, s)))
+        .collect();
+    let row = render_fleet_status(con, g, glyphs, theme, "Your fleet", &own, PANEL_OFFSET_Y);
+    let title = format!("{} fleet", whose(game, aimed));
+    render_fleet_status(con, g, glyphs, theme, &title, &enemy, row + 1);
+
+    // render text for the boards
+    let mut transform = con.transform.trans(BLOCK_SIZE * 5.5, BLOCK_SIZE * 16.0);
+    text::Text::new_color(theme.label, theme.heading_size)
+        .draw("Your board", glyphs, &con.draw_state, transform, g)
+        .unwrap();
+    transform = con.transform.trans(BLOCK_SIZE * 20.5, BLOCK_SIZE * 16.0);
+    text::Text::new_color(theme.label, theme.heading_size)
+        .draw(
+            &format!("{} board", whose(game, aimed)),
+            glyphs,
+            &con.draw_state,
+            transform,
+            g,
+        )
+        .unwrap();
+    transform = con.transform.trans(BLOCK_SIZE * 14.0, BLOCK_SIZE * 1.6);
+    text::Text::new_color(theme.title, theme.title_size)
+        .draw("Battle ship game", glyphs, &con.draw_state, transform, g)
+        .unwrap();
+    transform = con.transform.trans(BLOCK_SIZE * 2.0, BLOCK_SIZE * 18.0);
+    // paint the text
+    let aiming = format!(
+        "* Click on enemy board's grid, or aim with {}/{}/{}/{} and press {}, to attack.",
+        keys.up.label(),
+        keys.left.label(),
+        keys.down.label(),
+        keys.right.label(),
+        keys.fire.label()
+    );
+    let help = if game.players.len() == 2 {
+        format!(
+            "{} {} for a hint, {} for the heatmap.",
+            aiming,
+            keys.hint.label(),
+            keys.heatmap.label()
+        )
+    } else {
+        format!(
+            "{} {} or a small board picks the enemy.",
+            aiming,
+            keys.next_target.label()
+        )
+    };
+    text::Text::new_color(theme.text, theme.text_size)
+        .draw(
+            &format!("{} Theme: {}", help, theme.name),
+            glyphs,
+            &con.draw_state,
+            transform,
+            g,
+        )
+        .unwrap();
+}
+
+// tint every cell the viewer hasn't attacked by how likely it holds a ship
+pub fn render_heatmap(
+    con: &Context,
+    g: &mut G2d,
+    theme: &Theme,
+    heat: &[[f64; 10]; 10],
+    view: &Board,
+    x_offset: i32,
+    y_offset: i32,
+) {
+    let [r, gr, b, a] = theme.heat;
+    for x in 0..10u8 {
+        for y in 0..10u8 {
+            let pos = Position {
+                x,
+                y,
+                depth: Depth::Surface,
+            };
+            if view.get_cell_value(pos) != BoardCell::Empty {
+                continue;
+            }
+            let alpha = a * 0.85 * heat[x as usize][y as usize] as f32;
+            draw_cell_tint(
+                [r, gr, b, alpha],
+                view.grid(),
+                x as i32 + x_offset,
+                y as i32 + y_offset,
+                &row_context(con, view.grid(), y),
+                g,
+            );
+        }
+    }
+}
+
+// the selected weapon and its uses left below the fleets and the cells it
+// would hit from `target`
+pub fn render_arsenal(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    game: &GameState,
+    (weapon, aim, layer): (Weapon, ShipOrientation, Depth),
+    (target, keys): (Option<Position>, &KeyBindings),
+) {
+    if let (GameStatus::InProgress, Some(target)) = (game.status, target) {
+        let [r, gr, b, _] = theme.hover_valid;
+        let grid = game.rules().grid;
+        // a moving ship is shown where it would end up on our board
+        let (cells, x, y) = match weapon {
+            Weapon::Move => (
+                game.moved_ship(PlayerID::P1, target).unwrap_or_default(),
+                OWN_OFFSET_X,
+                OWN_OFFSET_Y,
+            ),
+            Weapon::Maneuver => (
+                game.maneuvered(PlayerID::P1, target)
+                    .map(|(_, cells)| cells)
+                    .unwrap_or_default(),
+                OWN_OFFSET_X,
+                OWN_OFFSET_Y,
+            ),
+            Weapon::Repair => (
+                game.repairable(PlayerID::P1, target)
+                    .map(|_| vec![target])
+                    .unwrap_or_default(),
+                OWN_OFFSET_X,
+                OWN_OFFSET_Y,
+            ),
+            _ => (
+                weapon.footprint(target, aim),
+                ENEMY_OFFSET_X,
+                ENEMY_OFFSET_Y,
+            ),
+        };
+        for pos in cells {
+            draw_cell_tint(
+                [r, gr, b, 0.35],
+                grid,
+                pos.x as i32 + x,
+                pos.y as i32 + y,
+                &row_context(con, grid, pos.y),
+                g,
+            );
+        }
+    }
+    // misses the enemy fleet may have moved onto since, worth another shot
+    let [r, gr, b, _] = theme.hint;
+    for pos in game
+        .stale_misses(PlayerID::P1, game.aim(PlayerID::P1))
+        .into_iter()
+        .filter(|p| p.depth == layer)
+    {
+        draw_cell_tint(
+            [r, gr, b, 0.25],
+            game.rules().grid,
+            pos.x as i32 + ENEMY_OFFSET_X,
+            pos.y as i32 + ENEMY_OFFSET_Y,
+            &row_context(con, game.rules().grid, pos.y),
+            g,
+        );
+    }
+
+    // below both fleet lists in the panel
+    let row = PANEL_OFFSET_Y as f64 + 3.0 + 2.0 * game.players[0].ships().len() as f64;
+    let arsenal = game.arsenal(PlayerID::P1);
+    let mut selected = match arsenal.left(weapon) {
+        Some(left) => format!("{} x{}", weapon.name(), left),
+        None => String::from(weapon.name()),
+    };
+    if weapon.directional() {
+        selected.push_str(match aim {
+            ShipOrientation::Horizontal => ", horizontal",
+            ShipOrientation::Vertical => ", vertical",
+        });
+    }
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.text, theme.label_size),
+        &selected,
+        PANEL_OFFSET_X as f64,
+        row,
+    );
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.label, theme.label_size),
+        &format!(
+            "{} next weapon, {} turn it",
+            keys.next_weapon.label(),
+            keys.turn_weapon.label()
+        ),
+        PANEL_OFFSET_X as f64,
+        row + 0.8,
+    );
+}
+
+// mark the suggested cell on the enemy board and say why above the help text
+pub fn render_hint(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    hint: &Hint,
+    grid: Grid,
+) {
+    let (x, y) = (
+        hint.pos.x as i32 + ENEMY_OFFSET_X,
+        hint.pos.y as i32 + ENEMY_OFFSET_Y,
+    );
+    let cell = &row_context(con, grid, hint.pos.y);
+    draw_cell_outline(theme.hint, 3.0, grid, x, y, cell, g);
+    draw_ring(theme.hint, x, y, cell, g);
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.hint, theme.text_size),
+        &format!("Hint: {}, {}", hint.pos.label(), hint.reason),
+        2.0,
+        17.1,
+    );
+}
+
+pub fn render_menu<A: Copy>(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    buttons: &[MenuButton<A>],
+    entry: Option<&str>,
+    mouse: [f64; 2],
+) {
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.title, theme.title_size),
+        "Battle ship game",
+        14.0,
+        4.0,
+    );
+    for button in buttons {
+        button.render(con, g, glyphs, theme, mouse);
+    }
+    // the name of a new profile being typed in
+    if let Some(name) = entry {
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.text, theme.heading_size),
+            &format!("New profile name: {}_", name),
+            12.0,
+            18.0,
+        );
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.label, theme.label_size),
+            "Enter to create, Escape to cancel",
+            12.0,
+            19.0,
+        );
+    }
+}
+
+// the current rating and how it got there, in the lower right of the stats screen
+fn render_rating(con: &Context, g: &mut G2d, glyphs: &mut Glyphs, theme: &Theme, rating: &Rating) {
+    let status = if rating.provisional() {
+        format!(
+            "provisional, {} of {} games",
+            rating.games, PROVISIONAL_GAMES
+        )
+    } else {
+        format!("{} games", rating.games)
+    };
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.text, theme.heading_size),
+        &format!("Rating: {} ({})", rating.label(), status),
+        24.0,
+        12.0,
+    );
+    if rating.history.len() < 2 {
+        return;
+    }
+    // scale the history into a 12x4 block area
+    let [x, y, w, h] = [
+        24.0 * BLOCK_SIZE,
+        12.5 * BLOCK_SIZE,
+        12.0 * BLOCK_SIZE,
+        4.0 * BLOCK_SIZE,
+    ];
+    rectangle(theme.button, [x, y, w, h], con.transform, g);
+    let low = rating.history.iter().cloned().fold(f64::INFINITY, f64::min);
+    let high = rating
+        .history
+        .iter()
+        .cloned()
+        .fold(f64::NEG_INFINITY, f64::max);
+    let range = (high - low).max(1.0);
+    let step = w / (rating.history.len() - 1) as f64;
+    let point = |i: usize, value: f64| [x + i as f64 * step, y + h - (value - low) / range * h];
+    for (i, pair) in rating.history.windows(2).enumerate() {
+        let [x1, y1] = point(i, pair[0]);
+        let [x2, y2] = point(i + 1, pair[1]);
+        line(theme.title, 1.0, [x1, y1, x2, y2], con.transform, g);
+    }
+}
+
+// career statistics of one profile, broken down by opponent and rules
+pub fn render_stats<A: Copy>(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    (name, profile): (&str, Option<&Profile>),
+    buttons: &[MenuButton<A>],
+    mouse: [f64; 2],
+) {
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.title, theme.title_size),
+        &format!("Statistics for {}", name),
+        3.0,
+        2.0,
+    );
+    let describe = |record: &Record| {
+        let average = match record.average_shots_to_win() {
+            Some(average) => format!("{:.1}", average),
+            None => String::from("-"),
+        };
+        let best = match record.best_game {
+            Some(best) => best.to_string(),
+            None => String::from("-"),
+        };
+        format!(
+            "{} played, {} won, {} lost, {:.0}% accuracy, {} shots to win, best {}",
+            record.played,
+            record.wins,
+            record.losses,
+            record.accuracy() * 100.0,
+            average,
+            best
+        )
+    };
+    let mut row = 4.0;
+    match profile {
+        Some(profile) if !profile.records.is_empty() => {
+            render_rating(con, g, glyphs, theme, &profile.rating);
+            render_text(
+                con,
+                g,
+                glyphs,
+                text::Text::new_color(theme.label, theme.label_size),
+                &format!(
+                    "The adaptive AI has studied {} of your fleets",
+                    profile.layout.games
+                ),
+                3.0,
+                17.0,
+            );
+            render_text(
+                con,
+                g,
+                glyphs,
+                text::Text::new_color(theme.text, theme.heading_size),
+                &format!("Career: {}", describe(&profile.career())),
+                3.0,
+                row,
+            );
+            row += 1.5;
+            for (key, record) in &profile.records {
+                render_text(
+                    con,
+                    g,
+                    glyphs,
+                    text::Text::new_color(theme.text, theme.text_size),
+                    &format!("vs {}", key),
+                    3.0,
+                    row,
+                );
+                render_text(
+                    con,
+                    g,
+                    glyphs,
+                    text::Text::new_color(theme.label, theme.label_size),
+                    &describe(record),
+                    11.0,
+                    row,
+                );
+                row += 0.9;
+            }
+        }
+        _ => render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.text, theme.text_size),
+            "No games recorded yet",
+            3.0,
+            row,
+        ),
+    }
+    for button in buttons {
+        button.render(con, g, glyphs, theme, mouse);
+    }
+}
+
+// everyone with a rated game, the current profile highlighted
+pub fn render_leaderboard<A: Copy>(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    (entries, current): (&[(&str, &Profile)], &str),
+    buttons: &[MenuButton<A>],
+    mouse: [f64; 2],
+) {
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.title, theme.title_size),
+        "Leaderboard",
+        3.0,
+        2.0,
+    );
+    if entries.is_empty() {
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.text, theme.text_size),
+            "No rated games yet",
+            3.0,
+            4.0,
+        );
+    }
+    // as many as fit above the buttons
+    for (place, (name, profile)) in entries.iter().take(14).enumerate() {
+        let color = if *name == current {
+            theme.title
+        } else {
+            theme.text
+        };
+        let row = 4.0 + place as f64 * 0.9;
+        let record = profile.career();
+        for (text, x) in [
+            (format!("{}.", place + 1), 3.0),
+            (name.to_string(), 4.5),
+            (profile.rating.label(), 14.0),
+            (
+                format!("{} played, {} won", record.played, record.wins),
+                17.0,
+            ),
+        ] {
+            render_text(
+                con,
+                g,
+                glyphs,
+                text::Text::new_color(color, theme.text_size),
+                &text,
+                x,
+                row,
+            );
+        }
+    }
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.label, theme.label_size),
+        "? marks a provisional rating",
+        24.0,
+        18.4,
+    );
+    for button in buttons {
+        button.render(con, g, glyphs, theme, mouse);
+    }
+}
+
+// one line per player under their board, and the turn each ship went down in
+// the side panel
+fn render_match_stats(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    game: &GameState,
+) {
+    let mut row = PANEL_OFFSET_Y as f64;
+    let aimed = game.aim(PlayerID::P1);
+    for (player, x, title) in [
+        (PlayerID::P1, OWN_OFFSET_X, String::from("Your shots")),
+        (
+            aimed,
+            ENEMY_OFFSET_X,
+            format!("{} shots", whose(game, aimed)),
+        ),
+    ] {
+        let stats = MatchStats::for_player(&game.shots, player);
+        let lines = [
+            format!(
+                "{}: {}, {} hits ({:.0}%)",
+                title,
+                stats.shots,
+                stats.hits,
+                stats.hit_rate() * 100.0
+            ),
+            format!("Longest hit streak: {}", stats.longest_streak),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            render_text(
+                con,
+                g,
+                glyphs,
+                text::Text::new_color(theme.text, theme.text_size),
+                line,
+                x as f64,
+                14.2 + i as f64 * 0.9,
+            );
+        }
+
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.text, theme.heading_size),
+            &format!(
+                "{} sank",
+                if player == PlayerID::P1 {
+                    String::from("You")
+                } else {
+                    who(game, player)
+                }
+            ),
+            PANEL_OFFSET_X as f64,
+            row,
+        );
+        row += 0.9;
+        for (kind, turn) in &stats.sink_turns {
+            render_text(
+                con,
+                g,
+                glyphs,
+                text::Text::new_color(theme.label, theme.label_size),
+                &format!("{} on shot {}", kind.name(), turn),
+                PANEL_OFFSET_X as f64,
+                row,
+            );
+            row += 0.8;
+        }
+        row += 0.6;
+    }
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.text, theme.text_size),
+        &format!("Game length: {}", format_duration(game.duration())),
+        PANEL_OFFSET_X as f64,
+        row,
+    );
+}
+
+// both fleets revealed with the stats of the match and what to do next
+pub fn render_game_over<A: Copy>(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    (game, analysis, layer): (&GameState, Option<&Analysis>, (Depth, &Binding)),
+    buttons: &[MenuButton<A>],
+    mouse: [f64; 2],
+) {
+    let message = if game.won(PlayerID::P1) {
+        "You won the game! :)"
+    } else {
+        "You lost the game :("
+    };
+    let aimed = game.aim(PlayerID::P1).index();
+    render_boards(
+        con,
+        g,
+        glyphs,
+        theme,
+        game,
+        (
+            &Board::revealed(&game.ships[0], &game.fired_on(PlayerID::P1)),
+            &Board::revealed(&game.ships[aimed], &game.attacks[0][aimed]),
+        ),
+        layer,
+    );
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.title, theme.title_size),
+        message,
+        13.0,
+        1.6,
+    );
+    render_match_stats(con, g, glyphs, theme, game);
+    if let Some(analysis) = analysis {
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.text, theme.text_size),
+            &format!("Your shots: {}", analysis.summary()),
+            OWN_OFFSET_X as f64,
+            16.4,
+        );
+    }
+    for button in buttons {
+        button.render(con, g, glyphs, theme, mouse);
+    }
+}
+
+// the costliest shots of the last game, the full list goes into the export
+pub fn render_analysis<A: Copy>(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    analysis: &Analysis,
+    buttons: &[MenuButton<A>],
+    mouse: [f64; 2],
+) {
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.title, theme.title_size),
+        "Shot analysis",
+        3.0,
+        2.0,
+    );
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.text, theme.heading_size),
+        &analysis.summary(),
+        3.0,
+        3.6,
+    );
+    let worst = analysis.worst();
+    if worst.is_empty() {
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(theme.text, theme.text_size),
+            "No inaccuracies, every shot was as good as the best one",
+            3.0,
+            5.0,
+        );
+    }
+    for (i, review) in worst.iter().take(13).enumerate() {
+        let color = match review.grade {
+            Grade::Blunder => theme.hover_invalid,
+            _ => theme.text,
+        };
+        render_text(
+            con,
+            g,
+            glyphs,
+            text::Text::new_color(color, theme.text_size),
+            &Analysis::describe(review),
+            3.0,
+            5.0 + i as f64 * 0.9,
+        );
+    }
+    for button in buttons {
+        button.render(con, g, glyphs, theme, mouse);
+    }
+}
+
+// the game being replayed, both fleets visible from the start
+pub fn render_replay<A: Copy>(
+    con: &Context,
+    g: &mut G2d,
+    glyphs: &mut Glyphs,
+    theme: &Theme,
+    (replay, layer): (&Replay, (Depth, &Binding)),
+    buttons: &[MenuButton<A>],
+    mouse: [f64; 2],
+) {
+    let game = replay.game();
+    let aimed = game.aim(PlayerID::P1).index();
+    render_boards(
+        con,
+        g,
+        glyphs,
+        theme,
+        game,
+        (
+            &Board::revealed(&game.ships[0], &game.fired_on(PlayerID::P1)),
+            &Board::revealed(&game.ships[aimed], &game.attacks[0][aimed]),
+        ),
+        layer,
+    );
+    let (played, total) = replay.progress();
+    render_text(
+        con,
+        g,
+        glyphs,
+        text::Text::new_color(theme.title, theme.title_size),
+        &format!("Replay, turn {} of {}", played, total),
+        12.0,
+        1.6,
+    );
+    for button in buttons {
+        button.render(con, g, glyphs, theme, mouse);
+    }
+}
+
+// draw the last settings error along the bottom of the window
+pub fn render_error(con: &Context, g: &mut G2d, glyphs: &mut Glyphs, theme: &Theme, error: &str) {
+    let transform = con.transform.trans(BLOCK_SIZE * 2.0, BLOCK_SIZE * 19.3);
+    text::Text::new_color(theme.hover_invalid, theme.label_size)
+        .draw(error, glyphs, &con.draw_state, transform, g)
+        .unwrap();
+}
+
+// a passing message in the same place, when there is no error to show
+pub fn render_notice(con: &Context, g: &mut G2d, glyphs: &mut Glyphs, theme: &Theme, notice: &str) {
+    let transform = con.transform.trans(BLOCK_SIZE * 2.0, BLOCK_SIZE * 19.3);
+    text::Text::new_color(theme.label, theme.label_size)
+        .draw(notice, glyphs, &con.draw_state, transform, g)
+        .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: u8, y: u8) -> Option<Position> {
+        Some(Position {
+            x,
+            y,
+            depth: Depth::Surface,
+        })
+    }
+
+    #[test]
+    fn the_mouse_picks_the_cell_under_it() {
+        let corner = [
+            ENEMY_OFFSET_X as f64 * BLOCK_SIZE,
+            ENEMY_OFFSET_Y as f64 * BLOCK_SIZE,
+        ];
+        let mouse = |x: f64, y: f64| [corner[0] + x * BLOCK_SIZE, corner[1] + y * BLOCK_SIZE];
+        let cell = |x, y| board_cell_at(mouse(x, y), ENEMY_OFFSET_X, ENEMY_OFFSET_Y, Grid::Square);
+        assert_eq!(cell(0.1, 0.1), at(0, 0));
+        assert_eq!(cell(4.5, 7.99), at(4, 7));
+        assert_eq!(cell(9.9, 9.9), at(9, 9));
+    }
+
+    #[test]
+    fn the_mouse_picks_the_hex_with_the_nearest_middle() {
+        let corner = [
+            ENEMY_OFFSET_X as f64 * BLOCK_SIZE,
+            ENEMY_OFFSET_Y as f64 * BLOCK_SIZE,
+        ];
+        let mouse = |x: f64, y: f64| [corner[0] + x * BLOCK_SIZE, corner[1] + y * BLOCK_SIZE];
+        let cell = |x, y| board_cell_at(mouse(x, y), ENEMY_OFFSET_X, ENEMY_OFFSET_Y, Grid::Hex);
+        // odd rows sit half a cell to the right
+        assert_eq!(cell(0.5, 0.5), at(0, 0));
+        assert_eq!(cell(1.0, 1.5), at(0, 1));
+        assert_eq!(cell(0.2, 1.5), None);
+        assert_eq!(cell(9.9, 1.5), at(9, 1));
+        // the points of a hex reach into the rows above and below
+        assert_eq!(cell(3.5, 2.95), at(3, 2));
+        assert_eq!(cell(3.1, 2.95), at(2, 3));
+    }
+
+    #[test]
+    fn the_mouse_off_the_board_picks_nothing() {
+        let cell = |x: f64, y: f64| {
+            board_cell_at(
+                [x * BLOCK_SIZE, y * BLOCK_SIZE],
+                OWN_OFFSET_X,
+                OWN_OFFSET_Y,
+                Grid::Square,
+            )
+        };
+        let (left, top) = (OWN_OFFSET_X as f64, OWN_OFFSET_Y as f64);
+        assert_eq!(cell(left - 0.1, top + 2.0), None);
+        assert_eq!(cell(left + 2.0, top + 10.1), None);
+        assert_eq!(cell(left + 10.0, top), None);
+        assert_eq!(cell(left, top), at(0, 0));
+    }
+}
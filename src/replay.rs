@@ -0,0 +1,53 @@
+use crate::game::{GameEvent, GameState, GameStatus, LoggedOrder};
+
+// plays the orders of a game back from its first position
+pub struct Replay {
+    game: GameState,
+    orders: Vec<LoggedOrder>,
+    next: usize,
+    // seconds between two shots
+    interval: f64,
+    timer: f64,
+}
+
+impl Replay {
+    pub fn new(start: GameState, orders: Vec<LoggedOrder>, interval: f64) -> Self {
+        Replay {
+            game: start,
+            orders,
+            next: 0,
+            interval,
+            timer: 0.0,
+        }
+    }
+
+    pub fn game(&self) -> &GameState {
+        &self.game
+    }
+
+    // turns played so far and in total
+    pub fn progress(&self) -> (usize, usize) {
+        (self.next, self.orders.len())
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next >= self.orders.len()
+    }
+
+    // fire the shots that are due, returning what happened for the animator
+    pub fn update(&mut self, dt: f64) -> Vec<GameEvent> {
+        self.timer += dt;
+        while self.timer >= self.interval && !self.finished() {
+            self.timer -= self.interval;
+            let (player, target, order) = self.orders[self.next];
+            self.game.set_aim(player, target);
+            // an order the game doesn't allow is passed over
+            let _ = self.game.fire(player, order);
+            self.next += 1;
+        }
+        if self.finished() && self.game.status == GameStatus::InProgress {
+            self.game.check_winner();
+        }
+        self.game.drain_events()
+    }
+}
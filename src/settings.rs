@@ -0,0 +1,659 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use piston_window::types::Color;
+use piston_window::Key;
+use serde::Deserialize;
+
+use crate::ai::{Difficulty, Placement};
+use crate::game::{Grid, MinePenalty, Rules, ShipKind};
+use crate::theme::Theme;
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(String, String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SettingsError::Io(path, e) => write!(f, "could not read {}: {}", path.display(), e),
+            SettingsError::Parse(path, e) => write!(f, "error in {}: {}", path.display(), e),
+            SettingsError::Invalid(field, message) => write!(f, "invalid {}: {}", field, message),
+        }
+    }
+}
+
+// the raw file layout, every section and field is optional
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct SettingsFile {
+    theme: ThemeSection,
+    window: WindowSection,
+    animation: AnimationSection,
+    game: GameSection,
+    rules: RulesSection,
+    placement: PlacementSection,
+    keys: HashMap<String, KeyNames>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeSection {
+    preset: Option<String>,
+    font: Option<String>,
+    title_size: Option<u32>,
+    heading_size: Option<u32>,
+    text_size: Option<u32>,
+    label_size: Option<u32>,
+    overlays: Option<bool>,
+    colors: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct WindowSection {
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct AnimationSection {
+    duration_ms: Option<f32>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct GameSection {
+    difficulty: Option<Difficulty>,
+    ranked: Option<bool>,
+}
+
+// the fleet layout strategy of each difficulty
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PlacementSection {
+    easy: Option<Placement>,
+    normal: Option<Placement>,
+    adaptive: Option<Placement>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct RulesSection {
+    fleet: Option<Vec<ShipKind>>,
+    ships_may_touch: Option<bool>,
+    special_weapons: Option<bool>,
+    ship_abilities: Option<bool>,
+    mobile_fleet: Option<bool>,
+    terrain: Option<bool>,
+    mine_penalty: Option<MinePenalty>,
+    repairs: Option<bool>,
+    fog: Option<bool>,
+    players: Option<usize>,
+    teams: Option<bool>,
+    depth: Option<bool>,
+    grid: Option<Grid>,
+}
+
+// one or more keys that trigger the same action
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding(Vec<Key>);
+
+impl Binding {
+    pub fn matches(&self, key: Key) -> bool {
+        self.0.contains(&key)
+    }
+
+    // the first key, as the help texts name it
+    pub fn label(&self) -> String {
+        self.0.first().map(|&k| key_name(k)).unwrap_or_default()
+    }
+}
+
+// a binding in the file is a single key name or a list of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum KeyNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+// actions that can be triggered from the keyboard
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    pub quit: Binding,
+    pub restart: Binding,
+    pub next_theme: Binding,
+    pub reload_settings: Binding,
+    // moving the targeting cursor, or between buttons on the other screens
+    pub up: Binding,
+    pub down: Binding,
+    pub left: Binding,
+    pub right: Binding,
+    // shoot at the cursor, or press the selected button
+    pub fire: Binding,
+    pub menu: Binding,
+    // suggest the next shot
+    pub hint: Binding,
+    // show placement probabilities on both boards, and save them as CSV
+    pub heatmap: Binding,
+    pub export_heatmap: Binding,
+    // save what the screen shows: the analysis after a game, the heatmap during one
+    pub save: Binding,
+    // take back the last shot and the computer's answer, casual games only
+    pub undo: Binding,
+    pub redo: Binding,
+    // pick the next special weapon and turn the ones that have a direction
+    pub next_weapon: Binding,
+    pub turn_weapon: Binding,
+    // with several opponents, show the next one's board and fire at it
+    pub next_target: Binding,
+    // in 3D games, show the other layer of water on both boards
+    pub next_layer: Binding,
+}
+
+impl KeyBindings {
+    pub fn new() -> Self {
+        KeyBindings {
+            quit: Binding(vec![Key::Escape]),
+            restart: Binding(vec![Key::R]),
+            next_theme: Binding(vec![Key::T]),
+            reload_settings: Binding(vec![Key::F5]),
+            up: Binding(vec![Key::Up, Key::W]),
+            down: Binding(vec![Key::Down, Key::S]),
+            left: Binding(vec![Key::Left, Key::A]),
+            right: Binding(vec![Key::Right, Key::D]),
+            fire: Binding(vec![Key::Return, Key::Space]),
+            menu: Binding(vec![Key::M]),
+            hint: Binding(vec![Key::H]),
+            heatmap: Binding(vec![Key::P]),
+            export_heatmap: Binding(vec![Key::E]),
+            save: Binding(vec![Key::F2]),
+            undo: Binding(vec![Key::U]),
+            redo: Binding(vec![Key::Y]),
+            next_weapon: Binding(vec![Key::Q]),
+            turn_weapon: Binding(vec![Key::X]),
+            next_target: Binding(vec![Key::Tab]),
+            next_layer: Binding(vec![Key::L]),
+        }
+    }
+
+    fn bind(&mut self, action: &str, binding: Binding) -> Result<(), SettingsError> {
+        match action {
+            "quit" => self.quit = binding,
+            "restart" => self.restart = binding,
+            "next_theme" => self.next_theme = binding,
+            "reload_settings" => self.reload_settings = binding,
+            "up" => self.up = binding,
+            "down" => self.down = binding,
+            "left" => self.left = binding,
+            "right" => self.right = binding,
+            "fire" => self.fire = binding,
+            "menu" => self.menu = binding,
+            "hint" => self.hint = binding,
+            "heatmap" => self.heatmap = binding,
+            "export_heatmap" => self.export_heatmap = binding,
+            "save" => self.save = binding,
+            "undo" => self.undo = binding,
+            "redo" => self.redo = binding,
+            "next_weapon" => self.next_weapon = binding,
+            "turn_weapon" => self.turn_weapon = binding,
+            "next_target" => self.next_target = binding,
+            "next_layer" => self.next_layer = binding,
+            _ => {
+                return Err(SettingsError::Invalid(
+                    format!("keys.{}", action),
+                    String::from("unknown action"),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    fn actions(&self) -> [(&'static str, &Binding); 20] {
+        [
+            ("quit", &self.quit),
+            ("restart", &self.restart),
+            ("next_theme", &self.next_theme),
+            ("reload_settings", &self.reload_settings),
+            ("up", &self.up),
+            ("down", &self.down),
+            ("left", &self.left),
+            ("right", &self.right),
+            ("fire", &self.fire),
+            ("menu", &self.menu),
+            ("hint", &self.hint),
+            ("heatmap", &self.heatmap),
+            ("export_heatmap", &self.export_heatmap),
+            ("save", &self.save),
+            ("undo", &self.undo),
+            ("redo", &self.redo),
+            ("next_weapon", &self.next_weapon),
+            ("turn_weapon", &self.turn_weapon),
+            ("next_target", &self.next_target),
+            ("next_layer", &self.next_layer),
+        ]
+    }
+
+    // a key may trigger only one action
+    fn check_unique(&self) -> Result<(), SettingsError> {
+        let actions = self.actions();
+        for (i, (action, binding)) in actions.iter().enumerate() {
+            for (other, taken) in &actions[..i] {
+                if let Some(&key) = binding.0.iter().find(|&&k| taken.matches(k)) {
+                    return Err(SettingsError::Invalid(
+                        format!("keys.{}", action),
+                        format!("{} is already bound to {}", key_name(key), other),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub theme: Theme,
+    pub window_size: [u32; 2],
+    pub animation_ms: f32,
+    pub difficulty: Difficulty,
+    // ranked games change ratings and can't be taken back
+    pub ranked: bool,
+    pub rules: Rules,
+    pub placements: HashMap<Difficulty, Placement>,
+    pub keys: KeyBindings,
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Settings {
+            theme: Theme::classic(),
+            window_size: [950, 500],
+            animation_ms: 500.0,
+            difficulty: Difficulty::Normal,
+            ranked: false,
+            rules: Rules::new(),
+            placements: HashMap::from([
+                (Difficulty::Easy, Placement::Random),
+                (Difficulty::Normal, Placement::Adaptive),
+                (Difficulty::Adaptive, Placement::Adaptive),
+            ]),
+            keys: KeyBindings::new(),
+        }
+    }
+
+    // how the computer lays out its fleet at the given difficulty
+    pub fn placement_for(&self, difficulty: Difficulty) -> Placement {
+        self.placements
+            .get(&difficulty)
+            .copied()
+            .unwrap_or(Placement::Random)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SettingsError> {
+        let text =
+            fs::read_to_string(path).map_err(|e| SettingsError::Io(path.to_path_buf(), e))?;
+        let file: SettingsFile =
+            toml::from_str(&text).map_err(|e| SettingsError::Parse(path.to_path_buf(), e))?;
+        Settings::from_file(file)
+    }
+
+    fn from_file(file: SettingsFile) -> Result<Self, SettingsError> {
+        let mut settings = Settings::new();
+
+        if let Some(name) = file.theme.preset {
+            settings.theme = Theme::preset(&name).ok_or_else(|| {
+                SettingsError::Invalid(
+                    String::from("theme.preset"),
+                    format!("no theme called \"{}\"", name),
+                )
+            })?;
+        }
+        let theme = &mut settings.theme;
+        if let Some(font) = file.theme.font {
+            theme.font = font;
+        }
+        for (field, value) in [
+            ("title_size", file.theme.title_size),
+            ("heading_size", file.theme.heading_size),
+            ("text_size", file.theme.text_size),
+            ("label_size", file.theme.label_size),
+        ] {
+            if let Some(size) = value {
+                if !(6..=96).contains(&size) {
+                    return Err(SettingsError::Invalid(
+                        format!("theme.{}", field),
+                        format!("{} is not between 6 and 96", size),
+                    ));
+                }
+            }
+        }
+        theme.title_size = file.theme.title_size.unwrap_or(theme.title_size);
+        theme.heading_size = file.theme.heading_size.unwrap_or(theme.heading_size);
+        theme.text_size = file.theme.text_size.unwrap_or(theme.text_size);
+        theme.label_size = file.theme.label_size.unwrap_or(theme.label_size);
+        theme.overlays = file.theme.overlays.unwrap_or(theme.overlays);
+        for (name, value) in &file.theme.colors {
+            let field = format!("theme.colors.{}", name);
+            let color = parse_color(value)
+                .map_err(|message| SettingsError::Invalid(field.clone(), message))?;
+            if !theme.set_color(name, color) {
+                return Err(SettingsError::Invalid(field, String::from("unknown color")));
+            }
+        }
+
+        let width = file.window.width.unwrap_or(settings.window_size[0]);
+        let height = file.window.height.unwrap_or(settings.window_size[1]);
+        if width < 200 || height < 200 {
+            return Err(SettingsError::Invalid(
+                String::from("window"),
+                format!("{}x{} is smaller than 200x200", width, height),
+            ));
+        }
+        settings.window_size = [width, height];
+
+        if let Some(ms) = file.animation.duration_ms {
+            if !(ms > 0.0 && ms <= 10_000.0) {
+                return Err(SettingsError::Invalid(
+                    String::from("animation.duration_ms"),
+                    format!("{} is not between 0 and 10000", ms),
+                ));
+            }
+            settings.animation_ms = ms;
+        }
+
+        if let Some(difficulty) = file.game.difficulty {
+            settings.difficulty = difficulty;
+        }
+        if let Some(ranked) = file.game.ranked {
+            settings.ranked = ranked;
+        }
+        for (difficulty, placement) in [
+            (Difficulty::Easy, file.placement.easy),
+            (Difficulty::Normal, file.placement.normal),
+            (Difficulty::Adaptive, file.placement.adaptive),
+        ] {
+            if let Some(placement) = placement {
+                settings.placements.insert(difficulty, placement);
+            }
+        }
+
+        if let Some(fleet) = file.rules.fleet {
+            settings.rules.fleet = fleet;
+        }
+        if let Some(may_touch) = file.rules.ships_may_touch {
+            settings.rules.ships_may_touch = may_touch;
+        }
+        if let Some(special_weapons) = file.rules.special_weapons {
+            settings.rules.special_weapons = special_weapons;
+        }
+        if let Some(ship_abilities) = file.rules.ship_abilities {
+            settings.rules.ship_abilities = ship_abilities;
+        }
+        if let Some(mobile_fleet) = file.rules.mobile_fleet {
+            settings.rules.mobile_fleet = mobile_fleet;
+        }
+        if let Some(terrain) = file.rules.terrain {
+            settings.rules.terrain = terrain;
+        }
+        if let Some(penalty) = file.rules.mine_penalty {
+            settings.rules.mine_penalty = penalty;
+        }
+        if let Some(repairs) = file.rules.repairs {
+            settings.rules.repairs = repairs;
+        }
+        if let Some(fog) = file.rules.fog {
+            settings.rules.fog = fog;
+        }
+        if let Some(players) = file.rules.players {
+            settings.rules.players = players;
+        }
+        if let Some(teams) = file.rules.teams {
+            settings.rules.teams = teams;
+        }
+        if let Some(depth) = file.rules.depth {
+            settings.rules.depth = depth;
+        }
+        if let Some(grid) = file.rules.grid {
+            settings.rules.grid = grid;
+        }
+        settings
+            .rules
+            .validate()
+            .map_err(|message| SettingsError::Invalid(String::from("rules"), message))?;
+
+        for (action, names) in &file.keys {
+            let names = match names {
+                KeyNames::One(name) => vec![name.clone()],
+                KeyNames::Many(names) => names.clone(),
+            };
+            let mut keys = vec![];
+            for name in &names {
+                keys.push(parse_key(name).ok_or_else(|| {
+                    SettingsError::Invalid(
+                        format!("keys.{}", action),
+                        format!("unknown key \"{}\"", name),
+                    )
+                })?);
+            }
+            if keys.is_empty() {
+                return Err(SettingsError::Invalid(
+                    format!("keys.{}", action),
+                    String::from("no keys given"),
+                ));
+            }
+            settings.keys.bind(action, Binding(keys))?;
+        }
+        settings.keys.check_unique()?;
+
+        Ok(settings)
+    }
+}
+
+// "#rrggbb" or "#rrggbbaa"
+fn parse_color(value: &str) -> Result<Color, String> {
+    let hex = value.trim_start_matches('#');
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "\"{}\" is not a color, expected \"#rrggbb\" or \"#rrggbbaa\"",
+            value
+        ));
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap() as f32 / 255.0;
+    let alpha = if hex.len() == 8 { channel(6) } else { 1.0 };
+    Ok([channel(0), channel(2), channel(4), alpha])
+}
+
+fn parse_key(name: &str) -> Option<Key> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "escape" | "esc" => Key::Escape,
+        "return" | "enter" => Key::Return,
+        "space" => Key::Space,
+        "tab" => Key::Tab,
+        "backspace" => Key::Backspace,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "left" => Key::Left,
+        "right" => Key::Right,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        other => {
+            // single letters and digits map onto their key codes
+            let mut chars = other.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_lowercase() || c.is_ascii_digit() => {
+                    Key::from(c as u32)
+                }
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+// the name a player knows a key by, digits without their "D"
+pub fn key_name(key: Key) -> String {
+    let name = format!("{:?}", key);
+    match name.strip_prefix('D') {
+        Some(digit) if digit.len() == 1 => String::from(digit),
+        _ => name,
+    }
+}
+
+// watches the settings file and reloads it whenever it is modified
+pub struct SettingsWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    since_check: f64,
+}
+
+impl SettingsWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        let modified = modified_time(&path);
+        SettingsWatcher {
+            path,
+            modified,
+            since_check: 0.0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // load the file, falling back to the defaults if there isn't one
+    pub fn load(&mut self) -> Result<Settings, SettingsError> {
+        self.modified = modified_time(&self.path);
+        if self.path.exists() {
+            Settings::load(&self.path)
+        } else {
+            Ok(Settings::new())
+        }
+    }
+
+    // called every frame with the elapsed seconds, looks at the file once a second
+    pub fn poll(&mut self, dt: f64) -> Option<Result<Settings, SettingsError>> {
+        self.since_check += dt;
+        if self.since_check < 1.0 {
+            return None;
+        }
+        self.since_check = 0.0;
+        if modified_time(&self.path) == self.modified {
+            return None;
+        }
+        Some(self.load())
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Settings, SettingsError> {
+        Settings::from_file(toml::from_str(text).expect("valid toml"))
+    }
+
+    fn error(text: &str) -> String {
+        parse(text).expect_err("should be rejected").to_string()
+    }
+
+    #[test]
+    fn an_empty_file_keeps_the_defaults() {
+        let settings = parse("").unwrap();
+        assert_eq!(settings.window_size, Settings::new().window_size);
+        assert_eq!(settings.rules.variant(), Rules::new().variant());
+    }
+
+    #[test]
+    fn out_of_range_numbers_name_the_field() {
+        assert_eq!(
+            error("[theme]\ntext_size = 200"),
+            "invalid theme.text_size: 200 is not between 6 and 96"
+        );
+        assert_eq!(
+            error("[window]\nwidth = 100\nheight = 300"),
+            "invalid window: 100x300 is smaller than 200x200"
+        );
+    }
+
+    #[test]
+    fn colors_must_be_hex_and_known() {
+        assert_eq!(
+            error("[theme.colors]\nwater = \"blue\""),
+            "invalid theme.colors.water: \"blue\" is not a color, expected \"#rrggbb\" or \"#rrggbbaa\""
+        );
+        assert_eq!(
+            error("[theme.colors]\nsky = \"#ffffff\""),
+            "invalid theme.colors.sky: unknown color"
+        );
+        assert_eq!(
+            parse_color("#ff000080").unwrap(),
+            [1.0, 0.0, 0.0, 128.0 / 255.0]
+        );
+    }
+
+    #[test]
+    fn keys_must_exist_and_bind_a_known_action() {
+        assert_eq!(
+            error("[keys]\nquit = \"Banana\""),
+            "invalid keys.quit: unknown key \"Banana\""
+        );
+        assert_eq!(
+            error("[keys]\ndance = \"D\""),
+            "invalid keys.dance: unknown action"
+        );
+        assert_eq!(
+            error("[keys]\nquit = []"),
+            "invalid keys.quit: no keys given"
+        );
+    }
+
+    #[test]
+    fn a_key_can_trigger_only_one_action() {
+        assert_eq!(
+            error("[keys]\nhint = \"R\""),
+            "invalid keys.hint: R is already bound to restart"
+        );
+        let settings = parse("[keys]\nhint = \"R\"\nrestart = [\"F9\", \"1\"]").unwrap();
+        assert_eq!(settings.keys.hint.label(), "R");
+        assert_eq!(settings.keys.restart.label(), "F9");
+        assert_eq!(key_name(Key::D1), "1");
+    }
+
+    #[test]
+    fn rules_that_dont_go_together_are_rejected() {
+        assert_eq!(
+            error("[rules]\ngrid = \"hex\"\nspecial_weapons = true"),
+            "invalid rules: hex boards are played without special weapons, ship abilities or a mobile fleet"
+        );
+    }
+
+    #[test]
+    fn unreadable_files_name_the_path() {
+        let path = Path::new("no/such/settings.toml");
+        let message = Settings::load(path).unwrap_err().to_string();
+        assert!(message.starts_with("could not read no/such/settings.toml: "));
+    }
+}
@@ -0,0 +1,279 @@
+use crate::ai::{add_shots, Difficulty, LayoutPrior, Placement, ShotHistory};
+use crate::game::{GameState, GameStatus, PlayerID, Rules};
+use crate::profile::{bot_name, GameResult, ProfileStore};
+use crate::settings::Settings;
+
+// a computer player: how it shoots and how it lays out its fleet
+#[derive(Debug, Clone, Copy)]
+pub struct Bot {
+    pub difficulty: Difficulty,
+    pub placement: Placement,
+}
+
+impl Bot {
+    pub fn new(settings: &Settings, difficulty: Difficulty) -> Self {
+        Bot {
+            difficulty,
+            placement: settings.placement_for(difficulty),
+        }
+    }
+}
+
+// what a side has seen of its opponents in earlier games: the shots its own
+// fleet received and where the opponents' fleets were
+#[derive(Default)]
+pub struct Experience {
+    pub fired_at: ShotHistory,
+    pub layouts: LayoutPrior,
+}
+
+impl Experience {
+    // learn from a finished game in which this side was `player`
+    pub fn learn(&mut self, game: &GameState, player: PlayerID) {
+        for shot in game.shots.iter().filter(|s| s.target == player) {
+            self.fired_at[shot.pos.x as usize][shot.pos.y as usize] += 1;
+        }
+        for (i, opponent) in game.players.iter().enumerate() {
+            if !game.allies(player, PlayerID(i)) {
+                self.layouts.record(&opponent.all_ship_positions());
+            }
+        }
+    }
+}
+
+// play a whole game between computer players, P1 shoots first; with more than
+// two seats the two bots take turns filling them
+pub fn play_bot_game(rules: &Rules, bots: [Bot; 2], experience: [&Experience; 2]) -> GameState {
+    let mut game = GameState::initialize(rules.clone());
+    let seats = 0..rules.players;
+    for i in seats.clone() {
+        game.set_layout_prior(PlayerID(i), experience[i % 2].layouts.clone());
+    }
+    let placements: Vec<Placement> = seats.clone().map(|i| bots[i % 2].placement).collect();
+    let histories: Vec<&ShotHistory> = seats.map(|i| &experience[i % 2].fired_at).collect();
+    game.start_with(&placements, &histories);
+    while game.status == GameStatus::InProgress {
+        let difficulty = bots[game.turn.index() % 2].difficulty;
+        game.ai_attack(game.turn, difficulty);
+        game.check_winner();
+    }
+    game.drain_events();
+    game
+}
+
+fn parse_strategy(name: &str) -> Result<Difficulty, String> {
+    Difficulty::from_name(name).ok_or_else(|| {
+        let names: Vec<&str> = Difficulty::all().iter().map(|d| d.name()).collect();
+        format!(
+            "unknown strategy \"{}\", expected one of {}",
+            name,
+            names.join(", ")
+        )
+    })
+}
+
+// the commands that run without opening a window
+pub const COMMANDS: [&str; 4] = ["simulate", "tournament", "placements", "leaderboard"];
+
+pub fn run(command: &str, args: &[String], settings: &Settings) -> Result<(), String> {
+    match command {
+        "simulate" => simulate(args, settings),
+        "tournament" => tournament(args, settings),
+        "placements" => placements(args, settings),
+        "leaderboard" => leaderboard(args),
+        _ => Err(format!("unknown command \"{}\"", command)),
+    }
+}
+
+fn parse_games(arg: Option<&String>) -> Result<u32, String> {
+    match arg {
+        Some(n) => n
+            .parse()
+            .map_err(|_| format!("\"{}\" is not a number of games", n)),
+        None => Ok(100),
+    }
+}
+
+// play `games` games between two strategies, adding the results and ratings
+// to the bots' profiles, returns the wins and shots fired in won games per side
+fn play_match(
+    store: &mut ProfileStore,
+    settings: &Settings,
+    a: Difficulty,
+    b: Difficulty,
+    games: u32,
+) -> ([u32; 2], [u32; 2]) {
+    let bots = [Bot::new(settings, a), Bot::new(settings, b)];
+    let mut wins = [0, 0];
+    let mut winning_shots = [0, 0];
+    // what each side learned about the other so far, for the adaptive bots
+    let mut experience = [Experience::default(), Experience::default()];
+    for i in 0..games {
+        // alternate who goes first so neither side gets the edge
+        let (first, second) = if i % 2 == 0 { (0, 1) } else { (1, 0) };
+        let game = play_bot_game(
+            &settings.rules,
+            [bots[first], bots[second]],
+            [&experience[first], &experience[second]],
+        );
+        for (player, side) in [(PlayerID::P1, first), (PlayerID::P2, second)] {
+            let (me, other) = (bots[side].difficulty, bots[1 - side].difficulty);
+            let result = GameResult::from_game(&game, player, &bot_name(other.name()));
+            store.record(&bot_name(me.name()), true, &result);
+            if result.won {
+                wins[side] += 1;
+                winning_shots[side] += result.shots;
+                store.rate(&bot_name(me.name()), &bot_name(other.name()));
+            }
+            experience[side].learn(&game, player);
+        }
+    }
+    (wins, winning_shots)
+}
+
+// `simulate <strategy> <strategy> [games]`, plays headless games and adds the
+// results to the bots' profiles
+fn simulate(args: &[String], settings: &Settings) -> Result<(), String> {
+    if args.len() < 2 || args.len() > 3 {
+        return Err(String::from(
+            "usage: battleship simulate <strategy> <strategy> [games]",
+        ));
+    }
+    let a = parse_strategy(&args[0])?;
+    let b = parse_strategy(&args[1])?;
+    let games = parse_games(args.get(2))?;
+
+    let path = ProfileStore::default_path();
+    let mut store = ProfileStore::open(&path).map_err(|e| e.to_string())?;
+    let (wins, winning_shots) = play_match(&mut store, settings, a, b, games);
+    store.save().map_err(|e| e.to_string())?;
+
+    for (side, strategy) in [(0, a), (1, b)] {
+        let average = if wins[side] > 0 {
+            winning_shots[side] as f64 / wins[side] as f64
+        } else {
+            0.0
+        };
+        let rating = store
+            .get(&bot_name(strategy.name()))
+            .map(|p| p.rating.label())
+            .unwrap_or_default();
+        println!(
+            "{:>8}: {} wins, {:.1} shots per win, rated {}",
+            strategy.name(),
+            wins[side],
+            average,
+            rating
+        );
+    }
+    println!("results saved to {}", path.display());
+    Ok(())
+}
+
+// `tournament [games]`, every strategy plays every other one
+fn tournament(args: &[String], settings: &Settings) -> Result<(), String> {
+    if args.len() > 1 {
+        return Err(String::from("usage: battleship tournament [games]"));
+    }
+    let games = parse_games(args.first())?;
+
+    let path = ProfileStore::default_path();
+    let mut store = ProfileStore::open(&path).map_err(|e| e.to_string())?;
+    let strategies = Difficulty::all();
+    for (i, &a) in strategies.iter().enumerate() {
+        for &b in &strategies[i + 1..] {
+            let (wins, winning_shots) = play_match(&mut store, settings, a, b, games);
+            // the shots each side needed to find the other's fleet
+            let mean = |side: usize| {
+                if wins[side] > 0 {
+                    format!("{:.1}", winning_shots[side] as f64 / wins[side] as f64)
+                } else {
+                    String::from("-")
+                }
+            };
+            println!(
+                "{} ({}) vs {} ({}): {} - {}, shots to win {} - {}",
+                a.name(),
+                settings.placement_for(a).name(),
+                b.name(),
+                settings.placement_for(b).name(),
+                wins[0],
+                wins[1],
+                mean(0),
+                mean(1)
+            );
+        }
+    }
+    store.save().map_err(|e| e.to_string())?;
+    print_leaderboard(&store);
+    println!("results saved to {}", path.display());
+    Ok(())
+}
+
+fn print_leaderboard(store: &ProfileStore) {
+    for (place, (name, profile)) in store.leaderboard().iter().enumerate() {
+        println!(
+            "{:>3}. {:<20} {:>6} ({} games)",
+            place + 1,
+            name,
+            profile.rating.label(),
+            profile.rating.games
+        );
+    }
+}
+
+// `placements [games] [shooter]`, how many shots the shooting strategy needs
+// to sink a fleet laid out by each placement strategy, lower is easier to find
+fn placements(args: &[String], settings: &Settings) -> Result<(), String> {
+    if args.len() > 2 {
+        return Err(String::from(
+            "usage: battleship placements [games] [shooter]",
+        ));
+    }
+    let games = parse_games(args.first())?;
+    let shooter = match args.get(1) {
+        Some(name) => parse_strategy(name)?,
+        None => Difficulty::Normal,
+    };
+    println!("{} shooting, {} games each", shooter.name(), games);
+    for placement in Placement::all() {
+        // only P1 shoots, the game is over once P2's fleet is sunk
+        let rules = Rules {
+            players: 2,
+            teams: false,
+            ..settings.rules.clone()
+        };
+        let mut fired_at = [[0; 10]; 10];
+        let mut total = 0;
+        for _ in 0..games {
+            let mut game = GameState::initialize(rules.clone());
+            game.start_with(
+                &[Placement::Random, placement],
+                &[&[[0; 10]; 10], &fired_at],
+            );
+            while game.status == GameStatus::InProgress {
+                game.ai_attack(PlayerID::P1, shooter);
+                game.check_winner();
+            }
+            game.drain_events();
+            add_shots(&mut fired_at, &game.shots, PlayerID::P1);
+            total += game.shots.len();
+        }
+        println!(
+            "{:>12}: {:.1} shots to win",
+            placement.name(),
+            total as f64 / games.max(1) as f64
+        );
+    }
+    Ok(())
+}
+
+// `leaderboard`, the ratings of everyone who has played a rated game
+fn leaderboard(args: &[String]) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err(String::from("usage: battleship leaderboard"));
+    }
+    let store = ProfileStore::open(&ProfileStore::default_path()).map_err(|e| e.to_string())?;
+    print_leaderboard(&store);
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::game::{BoardCell, GameState, PlayerID, Position, ShipKind, Shot};
+
+// how one player did over a single game, worked out from the shot log
+#[derive(Debug, Clone)]
+pub struct MatchStats {
+    pub shots: u32,
+    pub hits: u32,
+    pub longest_streak: u32,
+    // the opponent's ships in the order they were sunk, with the shot that did it
+    pub sink_turns: Vec<(ShipKind, u32)>,
+}
+
+impl MatchStats {
+    pub fn for_player(shots: &[Shot], player: PlayerID) -> Self {
+        let mut stats = MatchStats {
+            shots: 0,
+            hits: 0,
+            longest_streak: 0,
+            sink_turns: vec![],
+        };
+        let mut streak = 0;
+        for shot in shots.iter().filter(|s| s.player == player) {
+            stats.shots += 1;
+            if shot.hit {
+                stats.hits += 1;
+                streak += 1;
+                stats.longest_streak = stats.longest_streak.max(streak);
+            } else {
+                streak = 0;
+            }
+            if let Some(kind) = shot.sunk {
+                stats.sink_turns.push((kind, shot.turn));
+            }
+        }
+        stats
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.shots == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.shots as f64
+        }
+    }
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        format!("{}s", seconds)
+    } else {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    }
+}
+
+// both heatmaps of a game in one CSV file, one row per cell, from P1's side:
+// "enemy" is what P1 knows about the fleet they aim at, "own" what that enemy
+// knows about P1's
+pub fn write_heatmap_csv(game: &GameState, path: &Path) -> io::Result<()> {
+    let mut csv = String::from("board,cell,x,y,state,probability\n");
+    let enemy = game.aim(PlayerID::P1);
+    let boards = [("enemy", PlayerID::P1, enemy), ("own", enemy, PlayerID::P1)];
+    // in 3D games the submerged layer follows, its cells are labelled "B7 below"
+    for depth in game.rules().layers() {
+        for (board, player, target) in boards {
+            // shots fog of war hasn't reported yet show as pending
+            let view = game.seen(player, target);
+            let heat = game.heatmap(player, target, depth);
+            for y in 0..10 {
+                for x in 0..10 {
+                    let pos = Position { x, y, depth };
+                    let probability = heat[x as usize][y as usize];
+                    // hits on sunk ships are the only ones with nothing left to find
+                    let state = match view.get_cell_value(pos) {
+                        BoardCell::FailedAttack => "miss",
+                        BoardCell::SuccessfulAttack if probability == 0.0 => "sunk",
+                        BoardCell::SuccessfulAttack | BoardCell::DamagedShip => "hit",
+                        BoardCell::Island => "island",
+                        BoardCell::Mine => "mine",
+                        BoardCell::Repaired => "repaired",
+                        BoardCell::Pending => "pending",
+                        BoardCell::Empty | BoardCell::Ship => "unknown",
+                    };
+                    csv.push_str(&format!(
+                        "{},{},{},{},{},{:.4}\n",
+                        board,
+                        pos.label(),
+                        x,
+                        y,
+                        state,
+                        probability
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, csv)
+}
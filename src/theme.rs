@@ -0,0 +1,222 @@
+use piston_window::types::Color;
+
+// convert a 0xRRGGBB literal into a piston color
+const fn rgb(hex: u32) -> Color {
+    [
+        ((hex >> 16) & 0xff) as f32 / 255.0,
+        ((hex >> 8) & 0xff) as f32 / 255.0,
+        (hex & 0xff) as f32 / 255.0,
+        1.0,
+    ]
+}
+
+// the font shipped in the assets folder, used when a theme's can't be loaded
+pub const DEFAULT_FONT: &str = "FiraSans-Regular.ttf";
+
+// every color and font choice used by the renderer
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub grid_line: Color,
+    pub water: Color,
+    pub ship: Color,
+    pub damaged_ship: Color,
+    pub hit: Color,
+    pub miss: Color,
+    pub island: Color,
+    pub mine: Color,
+    // a hit the owner repaired, a ship is known to be there
+    pub repaired: Color,
+    // a shot whose result hasn't been reported yet
+    pub pending: Color,
+    pub sunk_outline: Color,
+    pub hover_valid: Color,
+    pub hover_invalid: Color,
+    pub last_shot: Color,
+    pub splash: Color,
+    // the cell a hint recommends
+    pub hint: Color,
+    // the heatmap overlay, its alpha is scaled by the probability
+    pub heat: Color,
+    pub title: Color,
+    pub text: Color,
+    pub label: Color,
+    pub afloat: Color,
+    pub damaged: Color,
+    pub sunk: Color,
+    pub button: Color,
+    pub button_hover: Color,
+    // draw a cross on hits and a dot on misses so they don't rely on color
+    pub overlays: bool,
+    pub hit_overlay: Color,
+    pub miss_overlay: Color,
+    pub font: String,
+    pub title_size: u32,
+    pub heading_size: u32,
+    pub text_size: u32,
+    pub label_size: u32,
+}
+
+impl Theme {
+    pub fn classic() -> Self {
+        Theme {
+            name: String::from("classic"),
+            background: [0.2, 0.2, 0.2, 1.0],
+            grid_line: rgb(0x000000),
+            water: rgb(0x00ffff),
+            ship: rgb(0x00ff00),
+            damaged_ship: rgb(0xb00000),
+            hit: rgb(0xff0000),
+            miss: rgb(0x000080),
+            island: rgb(0xc8a860),
+            mine: rgb(0x602060),
+            repaired: rgb(0xff9090),
+            pending: rgb(0x708090),
+            sunk_outline: rgb(0x000000),
+            hover_valid: rgb(0xffffff),
+            hover_invalid: rgb(0x808080),
+            last_shot: rgb(0xffd900),
+            splash: rgb(0x000000),
+            hint: rgb(0xff00ff),
+            heat: rgb(0xff4000),
+            title: rgb(0xffffff),
+            text: rgb(0xffffff),
+            label: rgb(0x808080),
+            afloat: rgb(0xffffff),
+            damaged: rgb(0xffff00),
+            sunk: rgb(0x808080),
+            button: rgb(0x404040),
+            button_hover: rgb(0x606060),
+            overlays: false,
+            hit_overlay: rgb(0x000000),
+            miss_overlay: rgb(0xffffff),
+            font: String::from(DEFAULT_FONT),
+            title_size: 32,
+            heading_size: 18,
+            text_size: 15,
+            label_size: 14,
+        }
+    }
+
+    // blue/orange palette from Okabe & Ito, avoids the red/green axis
+    pub fn deuteranopia() -> Self {
+        Theme {
+            name: String::from("deuteranopia"),
+            heat: rgb(0xcc79a7),
+            water: rgb(0x56b4e9),
+            ship: rgb(0xbbbbbb),
+            damaged_ship: rgb(0x9e5a00),
+            hit: rgb(0xe69f00),
+            miss: rgb(0x0b3c6e),
+            last_shot: rgb(0xf0e442),
+            damaged: rgb(0xe69f00),
+            sunk: rgb(0x999999),
+            overlays: true,
+            ..Theme::classic()
+        }
+    }
+
+    // protans see reds as dark, so hits use yellow rather than vermilion
+    pub fn protanopia() -> Self {
+        Theme {
+            name: String::from("protanopia"),
+            heat: rgb(0xcc79a7),
+            water: rgb(0x7fb8e0),
+            ship: rgb(0xd0d0d0),
+            damaged_ship: rgb(0xa08c00),
+            hit: rgb(0xf0e442),
+            miss: rgb(0x002f6c),
+            last_shot: rgb(0xffffff),
+            damaged: rgb(0xf0e442),
+            sunk: rgb(0x999999),
+            overlays: true,
+            ..Theme::classic()
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            name: String::from("high-contrast"),
+            heat: rgb(0xff00ff),
+            background: rgb(0x000000),
+            grid_line: rgb(0xffffff),
+            water: rgb(0x000000),
+            ship: rgb(0xffffff),
+            damaged_ship: rgb(0xff00ff),
+            hit: rgb(0xffff00),
+            miss: rgb(0x0050ff),
+            island: rgb(0x806000),
+            mine: rgb(0xa000ff),
+            repaired: rgb(0x808000),
+            pending: rgb(0x606060),
+            sunk_outline: rgb(0x00ffff),
+            hover_valid: rgb(0x00ff00),
+            hover_invalid: rgb(0xff0000),
+            last_shot: rgb(0xff8000),
+            splash: rgb(0xffffff),
+            hint: rgb(0x00ff80),
+            label: rgb(0xffffff),
+            damaged: rgb(0xffff00),
+            sunk: rgb(0xb0b0b0),
+            button: rgb(0x0000a0),
+            button_hover: rgb(0x0050ff),
+            overlays: true,
+            title_size: 34,
+            heading_size: 20,
+            text_size: 16,
+            label_size: 16,
+            ..Theme::classic()
+        }
+    }
+
+    pub fn preset(name: &str) -> Option<Theme> {
+        Theme::builtin().into_iter().find(|t| t.name == name)
+    }
+
+    // change one of the colors by its field name, false if there is no such color
+    pub fn set_color(&mut self, name: &str, color: Color) -> bool {
+        let field = match name {
+            "background" => &mut self.background,
+            "grid_line" => &mut self.grid_line,
+            "water" => &mut self.water,
+            "ship" => &mut self.ship,
+            "damaged_ship" => &mut self.damaged_ship,
+            "hit" => &mut self.hit,
+            "miss" => &mut self.miss,
+            "island" => &mut self.island,
+            "mine" => &mut self.mine,
+            "repaired" => &mut self.repaired,
+            "pending" => &mut self.pending,
+            "sunk_outline" => &mut self.sunk_outline,
+            "hover_valid" => &mut self.hover_valid,
+            "hover_invalid" => &mut self.hover_invalid,
+            "last_shot" => &mut self.last_shot,
+            "splash" => &mut self.splash,
+            "hint" => &mut self.hint,
+            "heat" => &mut self.heat,
+            "title" => &mut self.title,
+            "text" => &mut self.text,
+            "label" => &mut self.label,
+            "afloat" => &mut self.afloat,
+            "damaged" => &mut self.damaged,
+            "sunk" => &mut self.sunk,
+            "button" => &mut self.button,
+            "button_hover" => &mut self.button_hover,
+            "hit_overlay" => &mut self.hit_overlay,
+            "miss_overlay" => &mut self.miss_overlay,
+            _ => return false,
+        };
+        *field = color;
+        true
+    }
+
+    pub fn builtin() -> Vec<Theme> {
+        vec![
+            Theme::classic(),
+            Theme::deuteranopia(),
+            Theme::protanopia(),
+            Theme::high_contrast(),
+        ]
+    }
+}
@@ -1,37 +1,43 @@
 use piston_window::ellipse;
 use piston_window::line;
+use piston_window::polygon;
 use piston_window::rectangle;
 use piston_window::types::Color;
 use piston_window::Context;
+use piston_window::Ellipse;
 use piston_window::G2d;
+use piston_window::Rectangle;
+use piston_window::Transformed;
 
-pub fn draw_block(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
+use crate::game::{Grid, Position};
+
+pub fn draw_block(color: Color, grid: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
     let gui_x = (x as f64) * 25.0;
     let gui_y = (y as f64) * 25.0;
     rectangle(color, [gui_x, gui_y, 25.0, 25.0], con.transform, g);
     line(
-        [0.0, 0.0, 0.0, 1.0],
+        grid,
         1.0,
         [gui_x, gui_y, gui_x + 25.0, gui_y],
         con.transform,
         g,
     );
     line(
-        [0.0, 0.0, 0.0, 1.0],
+        grid,
         1.0,
         [gui_x, gui_y, gui_x, gui_y + 25.0],
         con.transform,
         g,
     );
     line(
-        [0.0, 0.0, 0.0, 1.0],
+        grid,
         1.0,
         [gui_x + 25.0, gui_y, gui_x + 25.0, gui_y + 25.0],
         con.transform,
         g,
     );
     line(
-        [0.0, 0.0, 0.0, 1.0],
+        grid,
         1.0,
         [gui_x, gui_y + 25.0, gui_x + 25.0, gui_y + 25.0],
         con.transform,
@@ -39,12 +45,224 @@ pub fn draw_block(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
     );
 }
 
-pub fn draw_circle(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
-    let gui_x = (x as f64 + 0.15) * 25.0;
-    let gui_y = (y as f64 + 0.15) * 25.0;
+// the corners of the hexagon in the block at (x, y), pulled in towards its
+// middle by `inset` of its size; a hexagon is a block wide with its points a
+// sixth of a block into the rows above and below, where they interlock with
+// the next row
+fn hex_corners(x: i32, y: i32, inset: f64) -> [[f64; 2]; 6] {
+    let (cx, cy) = ((x as f64 + 0.5) * 25.0, (y as f64 + 0.5) * 25.0);
+    let scale = 25.0 * (1.0 - inset);
+    [
+        (0.0, -2.0 / 3.0),
+        (0.5, -1.0 / 3.0),
+        (0.5, 1.0 / 3.0),
+        (0.0, 2.0 / 3.0),
+        (-0.5, 1.0 / 3.0),
+        (-0.5, -1.0 / 3.0),
+    ]
+    .map(|(dx, dy)| [cx + dx * scale, cy + dy * scale])
+}
+
+// the hexagon counterpart of `draw_block`
+pub fn draw_hex(color: Color, grid: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    let corners = hex_corners(x, y, 0.0);
+    polygon(color, &corners, con.transform, g);
+    draw_hex_border(grid, 2.0, &corners, con, g);
+}
+
+fn draw_hex_border(color: Color, width: f64, corners: &[[f64; 2]; 6], con: &Context, g: &mut G2d) {
+    for i in 0..6 {
+        let ([x1, y1], [x2, y2]) = (corners[i], corners[(i + 1) % 6]);
+        line(color, width / 2.0, [x1, y1, x2, y2], con.transform, g);
+    }
+}
+
+// the context to draw a cell in row `y` of a board in, the odd rows of a hex
+// board sit half a block to the right
+pub fn row_context(con: &Context, grid: Grid, y: u8) -> Context {
+    match grid {
+        Grid::Hex if y % 2 == 1 => con.trans(12.5, 0.0),
+        _ => *con,
+    }
+}
+
+// fill a cell inside its edges in the shape of the grid, for translucent
+// overlays
+pub fn draw_cell_tint(color: Color, grid: Grid, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    match grid {
+        Grid::Square => draw_tint(color, x, y, con, g),
+        Grid::Hex => polygon(color, &hex_corners(x, y, 0.08), con.transform, g),
+    }
+}
+
+// the border of a cell in the shape of the grid, used for highlighting it
+pub fn draw_cell_outline(
+    color: Color,
+    width: f64,
+    grid: Grid,
+    x: i32,
+    y: i32,
+    con: &Context,
+    g: &mut G2d,
+) {
+    match grid {
+        Grid::Square => draw_outline(color, width, x, y, con, g),
+        Grid::Hex => draw_hex_border(color, width, &hex_corners(x, y, width / 25.0), con, g),
+    }
+}
+
+// fill a cell inside its grid lines, for translucent overlays
+pub fn draw_tint(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    let gui_x = (x as f64) * 25.0;
+    let gui_y = (y as f64) * 25.0;
+    rectangle(
+        color,
+        [gui_x + 1.0, gui_y + 1.0, 23.0, 23.0],
+        con.transform,
+        g,
+    );
+}
+
+// draw only the border of a block, used for highlighting a cell
+pub fn draw_outline(color: Color, width: f64, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    let half = width / 2.0;
+    let gui_x = (x as f64) * 25.0 + half;
+    let gui_y = (y as f64) * 25.0 + half;
+    let size = 25.0 - width;
+    line(
+        color,
+        half,
+        [gui_x, gui_y, gui_x + size, gui_y],
+        con.transform,
+        g,
+    );
+    line(
+        color,
+        half,
+        [gui_x, gui_y, gui_x, gui_y + size],
+        con.transform,
+        g,
+    );
+    line(
+        color,
+        half,
+        [gui_x + size, gui_y, gui_x + size, gui_y + size],
+        con.transform,
+        g,
+    );
+    line(
+        color,
+        half,
+        [gui_x, gui_y + size, gui_x + size, gui_y + size],
+        con.transform,
+        g,
+    );
+}
+
+// draw an X across a block, marks a hit independently of its color
+pub fn draw_cross(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    let gui_x = (x as f64 + 0.25) * 25.0;
+    let gui_y = (y as f64 + 0.25) * 25.0;
+    let size = 25.0 * 0.5;
+    line(
+        color,
+        1.5,
+        [gui_x, gui_y, gui_x + size, gui_y + size],
+        con.transform,
+        g,
+    );
+    line(
+        color,
+        1.5,
+        [gui_x + size, gui_y, gui_x, gui_y + size],
+        con.transform,
+        g,
+    );
+}
+
+// draw a small dot in the middle of a block, marks a miss
+pub fn draw_dot(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    let gui_x = (x as f64 + 0.38) * 25.0;
+    let gui_y = (y as f64 + 0.38) * 25.0;
     ellipse(
         color,
-        [gui_x, gui_y, 25.0 * 0.7, 25.0 * 0.7],
+        [gui_x, gui_y, 25.0 * 0.24, 25.0 * 0.24],
+        con.transform,
+        g,
+    );
+}
+
+// draw a hollow circle inside a block
+pub fn draw_ring(color: Color, x: i32, y: i32, con: &Context, g: &mut G2d) {
+    let gui_x = (x as f64 + 0.2) * 25.0;
+    let gui_y = (y as f64 + 0.2) * 25.0;
+    Ellipse::new_border(color, 1.5).draw(
+        [gui_x, gui_y, 25.0 * 0.6, 25.0 * 0.6],
+        &con.draw_state,
+        con.transform,
+        g,
+    );
+}
+
+// draw a rounded outline around the cells covered by a ship, on a hex board
+// each of its cells is outlined on its own
+pub fn draw_ship_outline(
+    color: Color,
+    cells: &[Position],
+    grid: Grid,
+    (offset_x, offset_y): (i32, i32),
+    con: &Context,
+    g: &mut G2d,
+) {
+    if cells.is_empty() {
+        return;
+    }
+    if grid == Grid::Hex {
+        for p in cells {
+            let (x, y) = (p.x as i32 + offset_x, p.y as i32 + offset_y);
+            let con = row_context(con, grid, p.y);
+            draw_hex_border(color, 3.0, &hex_corners(x, y, 0.12), &con, g);
+        }
+        return;
+    }
+    // a bent ship is traced along the edges of its cells that face the water
+    let straight =
+        cells.iter().all(|p| p.x == cells[0].x) || cells.iter().all(|p| p.y == cells[0].y);
+    if !straight {
+        for p in cells {
+            let (x, y) = (
+                (p.x as i32 + offset_x) as f64 * 25.0,
+                (p.y as i32 + offset_y) as f64 * 25.0,
+            );
+            let open = |dx: i8, dy: i8| {
+                !cells
+                    .iter()
+                    .any(|q| q.x as i8 == p.x as i8 + dx && q.y as i8 == p.y as i8 + dy)
+            };
+            let edges = [
+                (open(0, -1), [x, y, x + 25.0, y]),
+                (open(0, 1), [x, y + 25.0, x + 25.0, y + 25.0]),
+                (open(-1, 0), [x, y, x, y + 25.0]),
+                (open(1, 0), [x + 25.0, y, x + 25.0, y + 25.0]),
+            ];
+            for (_, edge) in edges.into_iter().filter(|(open, _)| *open) {
+                line(color, 1.5, edge, con.transform, g);
+            }
+        }
+        return;
+    }
+    let min_x = cells.iter().map(|p| p.x).min().unwrap() as f64 + offset_x as f64;
+    let min_y = cells.iter().map(|p| p.y).min().unwrap() as f64 + offset_y as f64;
+    let max_x = cells.iter().map(|p| p.x).max().unwrap() as f64 + offset_x as f64;
+    let max_y = cells.iter().map(|p| p.y).max().unwrap() as f64 + offset_y as f64;
+    Rectangle::new_round_border(color, 8.0, 1.5).draw(
+        [
+            (min_x + 0.1) * 25.0,
+            (min_y + 0.1) * 25.0,
+            (max_x - min_x + 0.8) * 25.0,
+            (max_y - min_y + 0.8) * 25.0,
+        ],
+        &con.draw_state,
         con.transform,
         g,
     );
@@ -0,0 +1,316 @@
+use crate::game::{Depth, Position, ShipOrientation};
+
+// cells an airstrike covers on either side of the target
+const AIRSTRIKE_REACH: i8 = 2;
+const WEAPONS: usize = 11;
+
+// what a player can do with their turn: the shell is the ordinary single shot
+// and never runs out, the special weapons have a number of uses and the ship
+// abilities need their ship afloat
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Weapon {
+    Shell,
+    // reveals whether a 3x3 area holds a ship that hasn't been hit
+    Sonar,
+    // hits a row or column segment centred on the target
+    Airstrike,
+    // runs from the target to the edge of the board and stops at the first ship
+    Torpedo,
+    // hits the target and the four cells around it
+    ClusterBomb,
+    // the submarine's sonar, like the special weapon
+    Ping,
+    // the carrier's plane, reveals whether a whole row or column holds a ship
+    Recon,
+    // the battleship's shell, the player shoots again afterwards
+    DoubleShot,
+    // the destroyer moves one cell towards the target on its owner's board
+    Move,
+    // with a mobile fleet, move an undamaged ship along its axis or turn it
+    Maneuver,
+    // mend a damaged cell of one of our ships that is still afloat
+    Repair,
+}
+
+impl Weapon {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Weapon::Shell => "Shell",
+            Weapon::Sonar => "Sonar",
+            Weapon::Airstrike => "Airstrike",
+            Weapon::Torpedo => "Torpedo",
+            Weapon::ClusterBomb => "Cluster bomb",
+            Weapon::Ping => "Sonar ping",
+            Weapon::Recon => "Recon plane",
+            Weapon::DoubleShot => "Double shot",
+            Weapon::Move => "Move destroyer",
+            Weapon::Maneuver => "Manoeuvre",
+            Weapon::Repair => "Repair",
+        }
+    }
+
+    pub fn all() -> Vec<Weapon> {
+        vec![
+            Weapon::Shell,
+            Weapon::Sonar,
+            Weapon::Airstrike,
+            Weapon::Torpedo,
+            Weapon::ClusterBomb,
+            Weapon::Ping,
+            Weapon::Recon,
+            Weapon::DoubleShot,
+            Weapon::Move,
+            Weapon::Maneuver,
+            Weapon::Repair,
+        ]
+    }
+
+    // uses per game of a special weapon or a repair, None for the shell and
+    // the abilities
+    pub fn charges(&self) -> Option<u32> {
+        match self {
+            Weapon::Sonar => Some(2),
+            Weapon::Airstrike => Some(1),
+            Weapon::Torpedo => Some(2),
+            Weapon::ClusterBomb => Some(1),
+            Weapon::Repair => Some(2),
+            _ => None,
+        }
+    }
+
+    // turns of its owner before a ship can use its ability again
+    pub fn cooldown(&self) -> u32 {
+        match self {
+            Weapon::Ping => 3,
+            Weapon::Recon => 5,
+            Weapon::DoubleShot => 4,
+            Weapon::Move => 3,
+            _ => 0,
+        }
+    }
+
+    // in 3D games, whether the weapon gets to a layer: sonar only listens
+    // below the surface and the planes only see what is on it
+    pub fn reaches(&self, depth: Depth) -> bool {
+        match self {
+            Weapon::Sonar | Weapon::Ping => depth == Depth::Submerged,
+            Weapon::Airstrike | Weapon::ClusterBomb | Weapon::Recon => depth == Depth::Surface,
+            _ => true,
+        }
+    }
+
+    // whether the weapon is aimed at the player's own board, like moving or
+    // repairing a ship
+    pub fn own_board(&self) -> bool {
+        matches!(self, Weapon::Move | Weapon::Maneuver | Weapon::Repair)
+    }
+
+    // whether the footprint changes when the aim is turned
+    pub fn directional(&self) -> bool {
+        matches!(self, Weapon::Airstrike | Weapon::Torpedo | Weapon::Recon)
+    }
+
+    // the cells covered when aimed at `target`, in the order they are hit
+    pub fn footprint(&self, target: Position, orientation: ShipOrientation) -> Vec<Position> {
+        let along = |i: i8| match orientation {
+            ShipOrientation::Horizontal => (i, 0),
+            ShipOrientation::Vertical => (0, i),
+        };
+        let offsets: Vec<(i8, i8)> = match self {
+            Weapon::Shell
+            | Weapon::DoubleShot
+            | Weapon::Move
+            | Weapon::Maneuver
+            | Weapon::Repair => vec![(0, 0)],
+            Weapon::Sonar | Weapon::Ping => (-1..=1)
+                .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+                .collect(),
+            Weapon::Airstrike => (-AIRSTRIKE_REACH..=AIRSTRIKE_REACH).map(along).collect(),
+            Weapon::Torpedo => (0..10).map(along).collect(),
+            Weapon::Recon => (-9..10).map(along).collect(),
+            Weapon::ClusterBomb => vec![(0, 0), (-1, 0), (1, 0), (0, -1), (0, 1)],
+        };
+        offsets
+            .into_iter()
+            .map(|(dx, dy)| (target.x as i8 + dx, target.y as i8 + dy))
+            .filter(|&(x, y)| (0..10).contains(&x) && (0..10).contains(&y))
+            .map(|(x, y)| Position {
+                x: x as u8,
+                y: y as u8,
+                ..target
+            })
+            .collect()
+    }
+}
+
+// a weapon aimed at a cell, what a player does with their turn
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Order {
+    pub weapon: Weapon,
+    pub target: Position,
+    pub orientation: ShipOrientation,
+}
+
+impl Order {
+    pub fn shell(target: Position) -> Self {
+        Order {
+            weapon: Weapon::Shell,
+            target,
+            orientation: ShipOrientation::Horizontal,
+        }
+    }
+
+    pub fn footprint(&self) -> Vec<Position> {
+        self.weapon.footprint(self.target, self.orientation)
+    }
+}
+
+// the special weapons a player has left
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Arsenal {
+    left: [u32; WEAPONS],
+}
+
+impl Arsenal {
+    pub fn empty() -> Self {
+        Arsenal { left: [0; WEAPONS] }
+    }
+
+    // every charge of the given weapons
+    pub fn stocked(weapons: &[Weapon]) -> Self {
+        let mut arsenal = Arsenal::empty();
+        for &weapon in weapons {
+            arsenal.left[Arsenal::index(weapon)] = weapon.charges().unwrap_or(0);
+        }
+        arsenal
+    }
+
+    fn index(weapon: Weapon) -> usize {
+        Weapon::all()
+            .iter()
+            .position(|&w| w == weapon)
+            .expect("every weapon is listed")
+    }
+
+    // uses left, None for what isn't counted
+    pub fn left(&self, weapon: Weapon) -> Option<u32> {
+        weapon.charges().map(|_| self.left[Arsenal::index(weapon)])
+    }
+
+    pub fn available(&self, weapon: Weapon) -> bool {
+        self.left(weapon).is_none_or(|n| n > 0)
+    }
+
+    pub fn spend(&mut self, weapon: Weapon) {
+        let i = Arsenal::index(weapon);
+        self.left[i] = self.left[i].saturating_sub(1);
+    }
+}
+
+// a sonar sweep that found a ship, the area is worth searching
+#[derive(PartialEq, Debug, Clone)]
+pub struct Contact {
+    pub centre: Position,
+    pub area: Vec<Position>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: u8, y: u8) -> Position {
+        Position {
+            x,
+            y,
+            depth: Depth::Surface,
+        }
+    }
+
+    fn cells(weapon: Weapon, target: Position, orientation: ShipOrientation) -> Vec<(u8, u8)> {
+        weapon
+            .footprint(target, orientation)
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect()
+    }
+
+    #[test]
+    fn single_cell_orders_cover_their_target() {
+        for weapon in [
+            Weapon::Shell,
+            Weapon::DoubleShot,
+            Weapon::Move,
+            Weapon::Repair,
+        ] {
+            assert_eq!(
+                cells(weapon, at(4, 7), ShipOrientation::Vertical),
+                vec![(4, 7)]
+            );
+        }
+    }
+
+    #[test]
+    fn sonar_covers_the_square_around_its_target_on_the_board() {
+        assert_eq!(
+            Weapon::Sonar
+                .footprint(at(5, 5), ShipOrientation::Horizontal)
+                .len(),
+            9
+        );
+        assert_eq!(
+            cells(Weapon::Ping, at(0, 0), ShipOrientation::Horizontal),
+            vec![(0, 0), (0, 1), (1, 0), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn airstrike_follows_the_aim() {
+        assert_eq!(
+            cells(Weapon::Airstrike, at(1, 4), ShipOrientation::Horizontal),
+            vec![(0, 4), (1, 4), (2, 4), (3, 4)]
+        );
+        assert_eq!(
+            cells(Weapon::Airstrike, at(6, 5), ShipOrientation::Vertical),
+            vec![(6, 3), (6, 4), (6, 5), (6, 6), (6, 7)]
+        );
+    }
+
+    #[test]
+    fn torpedo_runs_from_its_target_to_the_edge() {
+        assert_eq!(
+            cells(Weapon::Torpedo, at(6, 2), ShipOrientation::Horizontal),
+            vec![(6, 2), (7, 2), (8, 2), (9, 2)]
+        );
+        assert_eq!(
+            Weapon::Torpedo
+                .footprint(at(3, 0), ShipOrientation::Vertical)
+                .len(),
+            10
+        );
+    }
+
+    #[test]
+    fn recon_sees_the_whole_line() {
+        let row = cells(Weapon::Recon, at(4, 8), ShipOrientation::Horizontal);
+        assert_eq!(row, (0..10).map(|x| (x, 8)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn cluster_bomb_is_cut_off_at_the_edge() {
+        assert_eq!(
+            cells(Weapon::ClusterBomb, at(9, 0), ShipOrientation::Horizontal),
+            vec![(9, 0), (8, 0), (9, 1)]
+        );
+    }
+
+    #[test]
+    fn footprints_stay_on_the_target_layer() {
+        let below = at(5, 5).at(Depth::Submerged);
+        for weapon in Weapon::all() {
+            assert!(weapon
+                .footprint(below, ShipOrientation::Vertical)
+                .iter()
+                .all(|p| p.depth == Depth::Submerged));
+        }
+    }
+}